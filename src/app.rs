@@ -1,12 +1,17 @@
 use crate::{
-    input::InputReader,
-    rendering::Renderer,
-    states::{State, StateManager},
+    game::board::GameOutcome,
+    input::{InputEvent, InputMode, InputReader},
+    rendering::{MirrorRenderer, Renderer},
+    states::{State, StateManager, StateManagerError, TransitionKind},
 };
 
 /// Represents the application.
 pub struct App<I, R> {
+    /// Called with the outcome of every finished game.
+    game_outcome_callback: Option<Box<dyn FnMut(GameOutcome)>>,
     input_reader: I,
+    /// Shows every frame a second time, to spectators.
+    mirror: Option<Box<dyn Renderer>>,
     renderer: R,
     state_manager: StateManager,
 }
@@ -19,26 +24,352 @@ where
     /// Constructs the application with the given renderer, input reader and an initial state.
     pub fn new(renderer: R, input_reader: I, first_state: Box<dyn State>) -> Self {
         App {
+            game_outcome_callback: None,
             input_reader,
+            mirror: None,
             renderer,
             state_manager: StateManager::new(first_state),
         }
     }
 
-    /// Starts the application.
-    pub fn run(&mut self) {
+    /// Starts recording the transitions between states, to debug the navigation. See `get_transition_log`.
+    pub fn enable_transition_log(&mut self) {
+        self.state_manager.enable_transition_log();
+    }
+
+    /// Gets the number of states on the stack. See `StateManager::get_depth`.
+    pub fn get_depth(&self) -> usize {
+        self.state_manager.get_depth()
+    }
+
+    /// Gets the transitions between states applied since the log was enabled, in order, or `None` if it is not.
+    pub fn get_transition_log(&self) -> Option<&[TransitionKind]> {
+        self.state_manager.get_transition_log()
+    }
+
+    /// Pushes a state on top of the initial one, to start the application past it. The initial state is shown once the pushed state is popped.
+    pub fn push_state(&mut self, state: Box<dyn State>) {
+        self.state_manager.push(state);
+    }
+
+    /// Sets a function to call with the outcome of every finished game, to follow the results without reading the screen.
+    pub fn set_game_outcome_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(GameOutcome) + 'static,
+    {
+        self.game_outcome_callback = Some(Box::new(callback));
+    }
+
+    /// Sets a renderer on which every frame is shown as well, such as a `WriteRenderer` streaming the game to spectators. It follows the layout of the main renderer.
+    pub fn set_mirror(&mut self, mirror: Box<dyn Renderer>) {
+        self.mirror = Some(mirror);
+    }
+
+    /// Clears the whole terminal, so that nothing of a frame drawn at another size is left over.
+    fn clear_screen(&self) -> Result<(), StateManagerError> {
+        if let Some(ref mirror) = self.mirror {
+            let _ = mirror.clear();
+        }
+        self.renderer.clear().map_err(StateManagerError::Rendering)
+    }
+
+    /// Renders the current state, on the mirror too if there is one.
+    fn render(&self) -> Result<(), StateManagerError> {
+        match self.mirror {
+            Some(ref mirror) => self
+                .state_manager
+                .render(&MirrorRenderer::new(&self.renderer, mirror.as_ref())),
+            None => self.state_manager.render(&self.renderer),
+        }
+    }
+
+    /// Passes the outcome of the game that just ended, if any, to the callback.
+    fn report_game_outcome(&mut self) {
+        if let Some(outcome) = self.state_manager.take_game_outcome() {
+            if let Some(ref mut callback) = self.game_outcome_callback {
+                callback(outcome);
+            }
+        }
+    }
+
+    /// Starts the application. Returns once the user quits, or as soon as rendering or reading the input fails.
+    pub fn run(&mut self) -> Result<(), StateManagerError> {
         loop {
-            if let Err(_) = self.state_manager.render(&self.renderer) {}
-            if let Some(state) = self.state_manager.get_current_state() {
-                if let Ok(i) = self.input_reader.read_input(state.get_input_mode()) {
-                    if let Ok(should_quit) = self.state_manager.handle_input(i) {
-                        if should_quit {
-                            break;
-                        }
-                        if let Err(_) = self.state_manager.render(&self.renderer) {}
+            self.render()?;
+            let should_quit = if let Some(delay) = self.state_manager.get_frame_delay() {
+                // While the current state is animated, it moves on to its next frame unless a key is pressed (or a tile clicked) meanwhile. States reading keys handle that input, the others skip their animation.
+                let mode = self
+                    .state_manager
+                    .get_current_state()
+                    .ok_or(StateManagerError::NoState)?
+                    .get_input_mode();
+                match self.input_reader.poll_key(mode, delay) {
+                    Some(InputEvent::Resize) => {
+                        self.clear_screen()?;
+                        false
                     }
+                    Some(input) if mode != InputMode::Line => {
+                        self.state_manager.handle_input(input)?
+                    }
+                    Some(_) => {
+                        self.state_manager.skip_animation();
+                        false
+                    }
+                    None => self.state_manager.tick(),
+                }
+            } else {
+                let mode = self
+                    .state_manager
+                    .get_current_state()
+                    .ok_or(StateManagerError::NoState)?
+                    .get_input_mode();
+                let input = self
+                    .input_reader
+                    .read_input(mode)
+                    .map_err(StateManagerError::Input)?;
+                if let InputEvent::Resize = input {
+                    // Only the app cares about resizes: the current state is rendered again, to the new size.
+                    self.clear_screen()?;
+                    continue;
                 }
+                self.state_manager.handle_input(input)?
+            };
+            if should_quit {
+                return Ok(());
+            }
+            self.report_game_outcome();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::{
+            board::Tile,
+            players::{Player, ScriptedPlayerController},
+        },
+        input::Key,
+        rendering::Error,
+        rendering::{BufferRenderer, Color, CursorPosition, ScreenSize},
+        scores::SharedScoreboard,
+        settings::SharedSettings,
+        states::{playing_state::PlayingState, StateTransition},
+    };
+    use std::{
+        cell::{Cell, RefCell},
+        collections::VecDeque,
+        io,
+        rc::Rc,
+        time::Duration,
+    };
+
+    /// An input reader handing out the given events, one per call. Polling gives the next event only if it is not a line, so that animations move on.
+    struct ScriptedInputReader {
+        events: RefCell<VecDeque<InputEvent>>,
+    }
+
+    impl ScriptedInputReader {
+        fn new(events: Vec<InputEvent>) -> Self {
+            ScriptedInputReader {
+                events: RefCell::new(events.into()),
+            }
+        }
+    }
+
+    impl InputReader for ScriptedInputReader {
+        fn poll_key(&self, _: InputMode, _: Duration) -> Option<InputEvent> {
+            let mut events = self.events.borrow_mut();
+            match events.front() {
+                Some(InputEvent::Line(_)) | None => None,
+                Some(_) => events.pop_front(),
+            }
+        }
+
+        fn read_key(&self) -> InputEvent {
+            self.events
+                .borrow_mut()
+                .pop_front()
+                .unwrap_or(InputEvent::Key(Key::Unknown))
+        }
+
+        fn read_key_or_click(&self) -> InputEvent {
+            self.read_key()
+        }
+
+        fn read_line(&self) -> Result<String, io::Error> {
+            match self.events.borrow_mut().pop_front() {
+                Some(InputEvent::Line(line)) => Ok(line),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "no more lines",
+                )),
             }
         }
     }
+
+    /// A state counting the events it is given, quitting on escape and closing itself on backspace.
+    struct CountingState {
+        handled: Rc<Cell<usize>>,
+        input_mode: InputMode,
+    }
+
+    impl State for CountingState {
+        fn get_input_mode(&self) -> InputMode {
+            self.input_mode
+        }
+
+        fn handle_input(&mut self, input: InputEvent) -> StateTransition {
+            self.handled.set(self.handled.get() + 1);
+            match input {
+                InputEvent::Key(Key::Escape) => StateTransition::Quit,
+                InputEvent::Key(Key::Backspace) => StateTransition::Pop,
+                _ => StateTransition::None,
+            }
+        }
+
+        fn render(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+            renderer.write("Counting").map(|_| ())
+        }
+    }
+
+    #[test]
+    fn resizes_are_not_given_to_the_current_state() {
+        let handled = Rc::new(Cell::new(0));
+        let state = CountingState {
+            handled: handled.clone(),
+            input_mode: InputMode::Key,
+        };
+        let input_reader = ScriptedInputReader::new(vec![
+            InputEvent::Resize,
+            InputEvent::Key(Key::Char('a')),
+            InputEvent::Resize,
+            InputEvent::Key(Key::Escape),
+        ]);
+        let mut app = App::new(BufferRenderer::new((80, 40)), input_reader, Box::new(state));
+        assert!(app.run().is_ok());
+        assert_eq!(handled.get(), 2);
+    }
+
+    #[test]
+    fn game_outcome_is_reported_once_the_game_ends() {
+        let players = vec![
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(0, 0), (0, 1), (0, 2)])),
+                Tile::O,
+            ),
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(1, 0), (1, 1)])),
+                Tile::X,
+            ),
+        ];
+        let game = PlayingState::with_players_starting(
+            players,
+            0,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        );
+        // Quit from the menu shown at the end of the game.
+        let input_reader = ScriptedInputReader::new(vec![InputEvent::Line("3".to_string())]);
+        let mut app = App::new(BufferRenderer::new((80, 40)), input_reader, Box::new(game));
+        let outcomes = Rc::new(RefCell::new(Vec::new()));
+        let reported = outcomes.clone();
+        app.set_game_outcome_callback(move |outcome| {
+            reported.borrow_mut().push((outcome.winner, outcome.moves))
+        });
+        assert!(app.run().is_ok());
+        let moves = vec![(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)];
+        assert!(*outcomes.borrow() == vec![(Some(Tile::O), moves)]);
+    }
+
+    /// A renderer that fails to draw anything.
+    struct FailingRenderer;
+
+    impl Renderer for FailingRenderer {
+        fn clear(&self) -> Result<(), Error> {
+            Err(Error::Other("no terminal".to_string()))
+        }
+
+        fn clear_current_line(&self) -> Result<(), Error> {
+            self.clear()
+        }
+
+        fn get_cursor_position(&self) -> CursorPosition {
+            (0, 0)
+        }
+
+        fn get_screen_size(&self) -> ScreenSize {
+            (80, 40)
+        }
+
+        fn set_background_color(&self, _: Color) -> Result<(), Error> {
+            self.clear()
+        }
+
+        fn set_bold(&self, _: bool) -> Result<(), Error> {
+            self.clear()
+        }
+
+        fn set_cursor_position(&self, _: CursorPosition) -> Result<(), Error> {
+            self.clear()
+        }
+
+        fn set_foreground_color(&self, _: Color) -> Result<(), Error> {
+            self.clear()
+        }
+
+        fn set_underline(&self, _: bool) -> Result<(), Error> {
+            self.clear()
+        }
+
+        fn write(&self, _: &str) -> Result<usize, Error> {
+            self.clear().map(|_| 0)
+        }
+    }
+
+    #[test]
+    fn run_stops_on_rendering_errors() {
+        let state = CountingState {
+            handled: Rc::new(Cell::new(0)),
+            input_mode: InputMode::Key,
+        };
+        let input_reader = ScriptedInputReader::new(vec![InputEvent::Key(Key::Char('a'))]);
+        let mut app = App::new(FailingRenderer, input_reader, Box::new(state));
+        assert!(matches!(app.run(), Err(StateManagerError::Rendering(_))));
+    }
+
+    #[test]
+    fn run_stops_on_input_errors() {
+        // The end of the input is reached while the state waits for a line.
+        let input_reader = ScriptedInputReader::new(Vec::new());
+        let state = CountingState {
+            handled: Rc::new(Cell::new(0)),
+            input_mode: InputMode::Line,
+        };
+        let mut app = App::new(BufferRenderer::new((80, 40)), input_reader, Box::new(state));
+        assert!(matches!(app.run(), Err(StateManagerError::Input(_))));
+    }
+
+    #[test]
+    fn popping_the_last_state_ends_the_run() {
+        let handled = Rc::new(Cell::new(0));
+        let state = CountingState {
+            handled: handled.clone(),
+            input_mode: InputMode::Key,
+        };
+        let input_reader = ScriptedInputReader::new(vec![
+            InputEvent::Key(Key::Backspace),
+            InputEvent::Key(Key::Backspace),
+            InputEvent::Key(Key::Escape),
+        ]);
+        let mut app = App::new(BufferRenderer::new((80, 40)), input_reader, Box::new(state));
+        app.push_state(Box::new(CountingState {
+            handled: handled.clone(),
+            input_mode: InputMode::Key,
+        }));
+        assert!(app.run().is_ok());
+        // The escape key was never read.
+        assert_eq!(handled.get(), 2);
+    }
 }
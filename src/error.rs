@@ -0,0 +1,42 @@
+use crate::game::board::ParseError;
+use std::fmt;
+
+/// Errors that can occure while saving or loading games and scores.
+pub enum GameError {
+    /// The file holds values that make no sense together.
+    Corrupt,
+    /// The file could not be read or written.
+    Io(std::io::Error),
+    /// The file could not be parsed. Contains a description of the problem.
+    Parse(String),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::Corrupt => write!(f, "the file is corrupt"),
+            GameError::Io(e) => write!(f, "could not access the file: {}", e),
+            GameError::Parse(message) => write!(f, "could not parse the file: {}", message),
+        }
+    }
+}
+
+impl From<std::io::Error> for GameError {
+    fn from(e: std::io::Error) -> GameError {
+        GameError::Io(e)
+    }
+}
+
+impl From<ParseError> for GameError {
+    fn from(e: ParseError) -> GameError {
+        match e {
+            ParseError::Inconsistent => GameError::Corrupt,
+            ParseError::InvalidDimensions => {
+                GameError::Parse("the board does not have the expected size".to_string())
+            }
+            ParseError::InvalidNumber => GameError::Parse("a number is malformed".to_string()),
+            ParseError::InvalidTile => GameError::Parse("a tile is unknown".to_string()),
+            ParseError::MissingLine => GameError::Parse("the text ends too early".to_string()),
+        }
+    }
+}
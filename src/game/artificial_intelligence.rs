@@ -1,4 +1,13 @@
 use super::board::{Board, GameResult, PlayingPosition, Tile};
+use crate::random;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 /// Represents a move that can be performed by the computer.
 #[derive(Clone, Copy)]
@@ -19,48 +28,590 @@ impl Move {
     }
 }
 
-/// A minimax algorithm that performs on a tic-tac-toe board. Returns the best move found.
+/// Tells how a score stored in the transposition table relates to the real score of the position, since alpha-beta pruning can stop a search before the exact score is known.
+#[derive(Clone, Copy)]
+enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The real score is at least this one.
+    Lower,
+    /// The real score is at most this one.
+    Upper,
+}
+
+/// The scores of the positions already searched, keyed by the canonical encoding of the board (see `canonical_key`) followed by the tile of the player to move.
+///
+/// On an empty 3x3 board, it brings the number of searched positions from about 30000 down to about 8000, and to about 1500 when symmetric positions share their entries.
+type TranspositionTable = HashMap<String, (i32, Bound)>;
+
+/// What the positions of a search share.
+struct Search<'a> {
+    /// Tells whether the search must stop before it is over. Checked at every position.
+    should_stop: &'a dyn Fn() -> bool,
+    table: &'a mut TranspositionTable,
+}
+
+/// The eight symmetries of a square board (four rotations, with or without a reflection), as functions mapping coordinates given the highest coordinate of the board.
+const SYMMETRIES: [fn(u8, u8, u8) -> PlayingPosition; 8] = [
+    |x, y, _| (x, y),
+    |x, y, n| (n - y, x),
+    |x, y, n| (n - x, n - y),
+    |x, y, n| (y, n - x),
+    |x, y, n| (n - x, y),
+    |x, y, n| (x, n - y),
+    |x, y, _| (y, x),
+    |x, y, n| (n - y, n - x),
+];
+
+/// Encodes a board the same way as `Board::encode`, except that all its symmetric equivalents get the same encoding: the smallest one among the eight symmetries.
+fn canonical_key(board: &Board) -> String {
+    let size = board.size();
+    SYMMETRIES
+        .iter()
+        .map(|symmetry| {
+            let mut key = String::with_capacity((size as usize) * (size as usize));
+            for y in 0..size {
+                for x in 0..size {
+                    let (sx, sy) = symmetry(x, y, size - 1);
+                    key.push(board.get(sx, sy).to_save_char());
+                }
+            }
+            key
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// The score of a win found right away. Must stay greater than the deepest search, whatever the size of the board.
+const WIN_SCORE: i32 = 1000;
+
+/// The highest score `evaluate` can give, so that a position that is not finished never looks better than a win.
+const MAX_EVALUATION: i32 = WIN_SCORE / 2;
+
+/// Estimates how good an unfinished position is for the given player, for searches that stop before the end of the game.
+///
+/// Each line that only holds tiles of the player (and empty spots) scores the square of its tile count, each line that only holds tiles of its opponent costs as much. Lines holding both kinds of tiles can't be won anymore and score nothing.
+pub fn evaluate(board: &Board, player: Tile) -> i32 {
+    let opponent = player.opponent();
+    let mut score = 0;
+    for solution in board.get_solutions() {
+        let (mut own, mut theirs) = (0, 0);
+        for &(x, y) in solution.iter() {
+            match *board.get(x, y) {
+                t if t == player => own += 1,
+                t if t == opponent => theirs += 1,
+                _ => {}
+            }
+        }
+        if theirs == 0 {
+            score += own * own;
+        } else if own == 0 {
+            score -= theirs * theirs;
+        }
+    }
+    score.clamp(-MAX_EVALUATION, MAX_EVALUATION)
+}
+
+/// A minimax algorithm that performs on a tic-tac-toe board. Returns the best move found, picked at random among equally good ones.
 pub fn minimax(board: &mut Board, player: Tile) -> Move {
+    if board.status().is_finished() {
+        return search_to_end(board, player, 0, &mut TranspositionTable::new());
+    }
+    search_root(board, player, i32::MAX, &|| false).unwrap_or_else(|| Move::with_score(0))
+}
+
+/// Indicates whether playing the given spot gives up a better outcome than the best move would get, with perfect play afterwards: a draw when a win was in reach, or a loss when a draw was. Winning more slowly is not a blunder.
+///
+/// Only the scores are needed, so no random choice is made: checking a move does not change the games played afterwards.
+pub fn is_blunder(board: &Board, player: Tile, spot: PlayingPosition) -> bool {
+    // 1 for a win of the player, 0 for a draw and -1 for a loss.
+    let outcome = |score: i32| {
+        if player == Tile::X {
+            score.signum()
+        } else {
+            -score.signum()
+        }
+    };
+    let search = |board: &mut Board, player: Tile| {
+        search_to_end(board, player, 0, &mut TranspositionTable::new()).score
+    };
+    let mut temp_board = board.clone();
+    let best = outcome(search(&mut temp_board, player));
+    if temp_board.set(spot.0, spot.1, player).is_err() {
+        return false;
+    }
+    outcome(search(&mut temp_board, player.opponent())) < best
+}
+
+/// Finds a move that keeps the game heading to a draw, for a sparring partner that leaves the winning to its opponent. Returns the move and its score for the given player, as `minimax` would score it.
+///
+/// Moves leading to a draw are preferred, then wins (the slowest first), then losses (the slowest first), so that it only takes a win when every other move loses. Equally good moves are picked at random.
+pub fn minimax_defensive(board: &mut Board, player: Tile) -> Move {
+    let mut best_rank: Option<i32> = None;
+    let mut best_moves = Vec::new();
+    let mut table = TranspositionTable::new();
+    for spot in board.get_available_spots() {
+        let _ = board.set(spot.0, spot.1, player);
+        let score = search_to_end(board, player.opponent(), 1, &mut table).score;
+        board.reset(spot.0, spot.1);
+        let own_score = if player == Tile::X { score } else { -score };
+        let rank = match own_score {
+            0 => 0,
+            s if s > 0 => -s,
+            s => s - WIN_SCORE,
+        };
+        if best_rank.is_none_or(|best| rank > best) {
+            best_rank = Some(rank);
+            best_moves.clear();
+        }
+        if best_rank == Some(rank) {
+            best_moves.push(Move::new(spot, score));
+        }
+    }
+    if best_moves.is_empty() {
+        return Move::with_score(0);
+    }
+    best_moves[random::gen_range(0, best_moves.len())]
+}
+
+/// A minimax algorithm with iterative deepening that can be stopped from another thread, for searches that may take long on large boards. Once `cancel` is set, returns the best move of the deepest completed iteration, or any legal move if none completed.
+pub fn minimax_cancellable(board: &mut Board, player: Tile, cancel: Arc<AtomicBool>) -> Move {
+    minimax_deepening(board, player, &|| cancel.load(Ordering::Relaxed))
+}
+
+/// A minimax algorithm with iterative deepening: searches one more move ahead at each iteration, until the whole game is explored or the time budget runs out. Returns the best move of the deepest completed iteration.
+pub fn minimax_timed(board: &mut Board, player: Tile, budget: Duration) -> Move {
+    let deadline = Instant::now() + budget;
+    minimax_deepening(board, player, &|| Instant::now() >= deadline)
+}
+
+/// Searches one more move ahead at each iteration, until the whole game is explored or `should_stop` returns true. Returns the best move of the deepest completed iteration.
+fn minimax_deepening(board: &mut Board, player: Tile, should_stop: &dyn Fn() -> bool) -> Move {
     let available_spots = board.get_available_spots();
-    if available_spots.len() == 0 {
-        return Move::with_score(match board.compute_result() {
-            GameResult::Winner(Tile::O, _) => -10,
-            GameResult::Winner(Tile::X, _) => 10,
-            _ => 0,
-        });
+    if board.status().is_finished() || available_spots.is_empty() {
+        return search_to_end(board, player, 0, &mut TranspositionTable::new());
+    }
+    // Any legal move is better than nothing if not even the first iteration completes.
+    let mut best_move = Move::new(available_spots[0], 0);
+    for max_depth in 1..=available_spots.len() as i32 {
+        match search_root(board, player, max_depth, should_stop) {
+            Some(m) => best_move = m,
+            None => break,
+        }
     }
-    let mut moves = Vec::new();
+    best_move
+}
+
+/// Scores every available spot, searching at most `max_depth` moves ahead, and picks one at random among the best. Returns `None` if `should_stop` returns true before every spot was scored.
+fn search_root(
+    board: &mut Board,
+    player: Tile,
+    max_depth: i32,
+    should_stop: &dyn Fn() -> bool,
+) -> Option<Move> {
+    let available_spots = board.get_available_spots();
+    let opponent = player.opponent();
+    let mut best_score: Option<i32> = None;
+    let mut best_spots = Vec::new();
+    // The scores depend on the depth limit, so the table can't be shared between searches.
+    let mut table = TranspositionTable::new();
+    // The scores of the moves searched so far, so that a move symmetric to one of them is not searched again.
+    let mut scored_moves = HashMap::new();
     for spot in available_spots.iter() {
-        board.set(spot.0, spot.1, player.clone());
-        let m = Move::new(
-            *spot,
+        let _ = board.set(spot.0, spot.1, player);
+        let key = canonical_key(board);
+        let score = match scored_moves.get(&key) {
+            Some(&score) => Some(score),
+            None => {
+                // Only prune the moves that are strictly worse than the best one, so that ties get an exact score.
+                let (alpha, beta) = match best_score {
+                    Some(best) if player == Tile::X => (best - 1, i32::MAX),
+                    Some(best) => (i32::MIN, best + 1),
+                    None => (i32::MIN, i32::MAX),
+                };
+                let score = minimax_ab(
+                    board,
+                    opponent,
+                    1,
+                    max_depth,
+                    alpha,
+                    beta,
+                    &mut Search {
+                        should_stop,
+                        table: &mut table,
+                    },
+                )
+                .map(|m| m.score);
+                // A pruned move only gets a bound, but it is enough to tell that its symmetric moves are worse than the best one too.
+                if let Some(score) = score {
+                    scored_moves.insert(key, score);
+                }
+                score
+            }
+        };
+        board.reset(spot.0, spot.1);
+        let score = score?;
+        let is_better = best_score.is_none_or(|best| {
             if player == Tile::X {
-                minimax(board, Tile::O)
+                score > best
             } else {
-                minimax(board, Tile::X)
+                score < best
             }
-            .score,
+        });
+        if is_better {
+            best_score = Some(score);
+            best_spots.clear();
+        }
+        if best_score == Some(score) {
+            best_spots.push(*spot);
+        }
+    }
+    Some(Move::new(
+        best_spots[random::gen_range(0, best_spots.len())],
+        best_score.unwrap_or(0),
+    ))
+}
+
+/// The minimax algorithm with alpha-beta pruning. `alpha` is the score X is already assured of, `beta` the one O is already assured of. Branches that cannot change the outcome are skipped. Returns `None` if `should_stop` returns true before the search is over, which is checked at every position.
+///
+/// Wins are worth less the deeper they are found (and losses cost less), so that the fastest win and the slowest loss are preferred. Positions `max_depth` moves deep that are not finished are scored by `evaluate`.
+fn minimax_ab(
+    board: &mut Board,
+    player: Tile,
+    depth: i32,
+    max_depth: i32,
+    mut alpha: i32,
+    mut beta: i32,
+    search: &mut Search,
+) -> Option<Move> {
+    match board.status() {
+        GameResult::Winner(Tile::O, _) => return Some(Move::with_score(depth - WIN_SCORE)),
+        GameResult::Winner(Tile::X, _) => return Some(Move::with_score(WIN_SCORE - depth)),
+        GameResult::Draw => return Some(Move::with_score(0)),
+        _ if depth >= max_depth => return Some(Move::with_score(evaluate(board, Tile::X))),
+        _ if (search.should_stop)() => return None,
+        _ => {}
+    }
+    let mut key = canonical_key(board);
+    key.push(player.to_save_char());
+    if let Some(&(score, bound)) = search.table.get(&key) {
+        match bound {
+            Bound::Exact => return Some(Move::with_score(score)),
+            Bound::Lower => alpha = alpha.max(score),
+            Bound::Upper => beta = beta.min(score),
+        }
+        if alpha >= beta {
+            return Some(Move::with_score(score));
+        }
+    }
+    let (initial_alpha, initial_beta) = (alpha, beta);
+    let available_spots = board.get_available_spots();
+    let mut best_move: Option<Move> = None;
+    for spot in available_spots.iter() {
+        let _ = board.set(spot.0, spot.1, player);
+        let result = minimax_ab(
+            board,
+            player.opponent(),
+            depth + 1,
+            max_depth,
+            alpha,
+            beta,
+            search,
         );
-        board.set(spot.0, spot.1, Tile::Empty);
-        moves.push(m);
-    }
-    let mut best_move = 0;
-    if player == Tile::X {
-        let mut best_score = -10000;
-        for (i, m) in moves.iter().enumerate() {
-            if m.score > best_score {
-                best_score = m.score;
-                best_move = i;
+        board.reset(spot.0, spot.1);
+        // A stopped search leaves no score in the table, since it is not known.
+        let m = Move::new(*spot, result?.score);
+        if player == Tile::X {
+            if best_move.is_none_or(|b| m.score > b.score) {
+                best_move = Some(m);
             }
+            alpha = alpha.max(m.score);
+        } else {
+            if best_move.is_none_or(|b| m.score < b.score) {
+                best_move = Some(m);
+            }
+            beta = beta.min(m.score);
+        }
+        if alpha >= beta {
+            break;
         }
+    }
+    let best_move = best_move.unwrap_or_else(|| Move::with_score(0));
+    let bound = if best_move.score <= initial_alpha {
+        Bound::Upper
+    } else if best_move.score >= initial_beta {
+        Bound::Lower
     } else {
-        let mut best_score = 10000;
-        for (i, m) in moves.iter().enumerate() {
-            if m.score < best_score {
-                best_score = m.score;
-                best_move = i;
+        Bound::Exact
+    };
+    search.table.insert(key, (best_move.score, bound));
+    Some(best_move)
+}
+
+/// Runs `minimax_ab` without a depth limit nor any way to stop it, so that the exact score is always found.
+fn search_to_end(
+    board: &mut Board,
+    player: Tile,
+    depth: i32,
+    table: &mut TranspositionTable,
+) -> Move {
+    minimax_ab(
+        board,
+        player,
+        depth,
+        i32::MAX,
+        i32::MIN,
+        i32::MAX,
+        &mut Search {
+            should_stop: &|| false,
+            table,
+        },
+    )
+    .unwrap_or_else(|| Move::with_score(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_blunder_finds_the_losing_move_of_a_game() {
+        // o answers the center on an edge, which loses, and x goes on to win.
+        let moves = [
+            ((1, 1), Tile::X),
+            ((1, 0), Tile::O),
+            ((0, 2), Tile::X),
+            ((2, 0), Tile::O),
+            ((0, 0), Tile::X),
+            ((0, 1), Tile::O),
+            ((2, 2), Tile::X),
+        ];
+        let mut board = Board::new();
+        let mut blunders = Vec::new();
+        for (i, &(spot, tile)) in moves.iter().enumerate() {
+            if is_blunder(&board, tile, spot) {
+                blunders.push(i);
             }
+            let _ = board.set(spot.0, spot.1, tile);
         }
+        assert!(board.status().winner() == Some(&Tile::X));
+        assert_eq!(blunders, vec![1]);
+    }
+
+    #[test]
+    fn minimax_cancellable_returns_a_legal_move_once_cancelled() {
+        let mut board = Board::with_size(5);
+        let _ = board.set(2, 2, Tile::X);
+        let m = minimax_cancellable(&mut board, Tile::O, Arc::new(AtomicBool::new(true)));
+        assert!(board.is_empty(m.pos.0, m.pos.1));
+    }
+
+    #[test]
+    fn minimax_cancellable_searches_to_the_end_unless_cancelled() {
+        let mut board = Board::new();
+        for &(x, y, tile) in [(0, 0, Tile::O), (1, 1, Tile::X), (1, 0, Tile::O)].iter() {
+            let _ = board.set(x, y, tile);
+        }
+        // x must block the top row.
+        let m = minimax_cancellable(&mut board, Tile::X, Arc::new(AtomicBool::new(false)));
+        assert_eq!(m.pos, (2, 0));
+    }
+
+    #[test]
+    fn minimax_timed_returns_a_legal_move_without_time() {
+        let mut board = Board::with_size(4);
+        let _ = board.set(0, 0, Tile::X);
+        let m = minimax_timed(&mut board, Tile::O, Duration::from_secs(0));
+        assert!(board.is_empty(m.pos.0, m.pos.1));
+        assert_eq!(board.turn_count(), 1);
+    }
+
+    #[test]
+    fn minimax_timed_takes_the_win_on_a_small_board() {
+        let mut board = Board::new();
+        for &(x, y, tile) in [
+            (0, 0, Tile::X),
+            (1, 1, Tile::O),
+            (1, 0, Tile::X),
+            (2, 2, Tile::O),
+        ]
+        .iter()
+        {
+            let _ = board.set(x, y, tile);
+        }
+        let m = minimax_timed(&mut board, Tile::X, Duration::from_secs(10));
+        assert_eq!(m.pos, (2, 0));
+        assert!(m.score > 0);
+    }
+
+    #[test]
+    fn is_blunder_makes_no_random_choice() {
+        random::seed(7);
+        let expected = random::gen_range(0, 1000);
+        random::seed(7);
+        is_blunder(&Board::new(), Tile::X, (1, 0));
+        assert_eq!(random::gen_range(0, 1000), expected);
+    }
+
+    /// Scores a position the way `minimax_ab` does, with a plain minimax exploring every move.
+    fn plain_minimax_score(board: &mut Board, player: Tile, depth: i32) -> i32 {
+        match board.status() {
+            GameResult::Winner(Tile::O, _) => return depth - WIN_SCORE,
+            GameResult::Winner(Tile::X, _) => return WIN_SCORE - depth,
+            GameResult::Draw => return 0,
+            _ => {}
+        }
+        let mut scores = Vec::new();
+        for spot in board.get_available_spots() {
+            let _ = board.set(spot.0, spot.1, player);
+            scores.push(plain_minimax_score(board, player.opponent(), depth + 1));
+            board.reset(spot.0, spot.1);
+        }
+        if player == Tile::X {
+            scores.into_iter().max().unwrap()
+        } else {
+            scores.into_iter().min().unwrap()
+        }
+    }
+
+    /// Every position reached after the two first moves on a 3x3 board, x to play.
+    fn positions_after_two_moves() -> Vec<Board> {
+        let mut positions = Vec::new();
+        for x_spot in Board::new().get_available_spots() {
+            let (board, _) = Board::new().with_move(x_spot, Tile::X).ok().unwrap();
+            for o_spot in board.get_available_spots() {
+                positions.push(board.with_move(o_spot, Tile::O).ok().unwrap().0);
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn alpha_beta_scores_like_plain_minimax() {
+        for mut board in positions_after_two_moves() {
+            let expected = plain_minimax_score(&mut board, Tile::X, 0);
+            let m = search_to_end(&mut board, Tile::X, 0, &mut TranspositionTable::new());
+            assert_eq!(m.score, expected);
+        }
+    }
+
+    #[test]
+    fn minimax_prefers_the_fastest_win_and_the_slowest_loss() {
+        // x can win right away on the top right corner, or later by blocking o on the bottom left one, which threatens two lines.
+        let mut board = Board::new();
+        for &(x, y, tile) in [
+            (0, 0, Tile::X),
+            (1, 2, Tile::O),
+            (1, 0, Tile::X),
+            (2, 2, Tile::O),
+        ]
+        .iter()
+        {
+            let _ = board.set(x, y, tile);
+        }
+        for seed in 0..5 {
+            random::seed(seed);
+            let m = minimax(&mut board, Tile::X);
+            assert_eq!(m.pos, (2, 0));
+            assert_eq!(m.score, WIN_SCORE - 1);
+        }
+        // o can't stop both the column and the diagonal of x, but it is not lost before x plays again.
+        let mut board = Board::new();
+        for &(x, y, tile) in [
+            (0, 0, Tile::X),
+            (1, 0, Tile::O),
+            (2, 0, Tile::X),
+            (0, 2, Tile::O),
+            (2, 2, Tile::X),
+        ]
+        .iter()
+        {
+            let _ = board.set(x, y, tile);
+        }
+        assert_eq!(minimax(&mut board, Tile::O).score, WIN_SCORE - 2);
+    }
+
+    #[test]
+    fn minimax_picks_at_random_among_the_best_moves() {
+        // Every first move leads to a draw, so every spot can be picked.
+        let mut picked = Vec::new();
+        for seed in 0..20 {
+            random::seed(seed);
+            let m = minimax(&mut Board::new(), Tile::X);
+            assert_eq!(m.score, 0);
+            if !picked.contains(&m.pos) {
+                picked.push(m.pos);
+            }
+        }
+        assert!(picked.len() > 1);
+        random::seed(3);
+        let first = minimax(&mut Board::new(), Tile::X).pos;
+        random::seed(3);
+        assert_eq!(minimax(&mut Board::new(), Tile::X).pos, first);
+    }
+
+    #[test]
+    fn shared_transposition_table_keeps_the_scores_of_plain_minimax() {
+        // Every position has the same number of tiles, so the table can be shared between their searches.
+        let mut table = TranspositionTable::new();
+        for mut board in positions_after_two_moves() {
+            let expected = plain_minimax_score(&mut board, Tile::X, 0);
+            let m = search_to_end(&mut board, Tile::X, 0, &mut table);
+            assert_eq!(m.score, expected);
+        }
+        assert!(!table.is_empty());
+    }
+
+    #[test]
+    fn symmetric_boards_share_their_canonical_key() {
+        let corner_key = |x, y| {
+            let (board, _) = Board::new().with_move((x, y), Tile::X).ok().unwrap();
+            canonical_key(&board)
+        };
+        let key = corner_key(0, 0);
+        assert_eq!(corner_key(2, 0), key);
+        assert_eq!(corner_key(0, 2), key);
+        assert_eq!(corner_key(2, 2), key);
+        assert_ne!(corner_key(1, 1), key);
+        assert_ne!(corner_key(1, 0), key);
+        // A reflection along the diagonal.
+        let board = "xo.\n...\n...".parse::<Board>().ok().unwrap();
+        let reflected = "x..\no..\n...".parse::<Board>().ok().unwrap();
+        assert_eq!(canonical_key(&board), canonical_key(&reflected));
+    }
+
+    #[test]
+    fn skipping_symmetric_moves_keeps_the_best_score() {
+        // The search skips the moves symmetric to one already scored, which must not change the best score.
+        for mut board in positions_after_two_moves() {
+            let expected = plain_minimax_score(&mut board, Tile::X, 0);
+            assert_eq!(minimax(&mut board, Tile::X).score, expected);
+        }
+    }
+
+    #[test]
+    fn minimax_timed_keeps_to_its_budget_on_a_large_board() {
+        let mut board = Board::with_size(4);
+        let budget = Duration::from_millis(200);
+        let start = Instant::now();
+        let m = minimax_timed(&mut board, Tile::X, budget);
+        // Every position checks the deadline, so it is only overrun by the time to search one.
+        assert!(start.elapsed() < budget + Duration::from_millis(50));
+        assert!(board.get_available_spots().contains(&m.pos));
+    }
+
+    #[test]
+    fn evaluate_prefers_positions_with_more_open_lines() {
+        let with_x_at =
+            |spot: PlayingPosition| Board::new().with_move(spot, Tile::X).ok().unwrap().0;
+        // The center is on four lines, a corner on three and an edge on two.
+        let center = evaluate(&with_x_at((1, 1)), Tile::X);
+        let corner = evaluate(&with_x_at((0, 0)), Tile::X);
+        let edge = evaluate(&with_x_at((1, 0)), Tile::X);
+        assert_eq!((center, corner, edge), (4, 3, 2));
+        assert_eq!(evaluate(&with_x_at((1, 1)), Tile::O), -4);
+        // An o in a corner closes one line of each player: three open lines for x, two for o.
+        let board = with_x_at((1, 1)).with_move((0, 0), Tile::O).ok().unwrap().0;
+        assert_eq!(evaluate(&board, Tile::X), 1);
     }
-    moves[best_move]
 }
@@ -1,3 +1,3 @@
-mod artificial_intelligence;
+pub mod artificial_intelligence;
 pub mod board;
 pub mod players;
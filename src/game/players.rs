@@ -1,20 +1,115 @@
 use crate::{
     game::{
-        artificial_intelligence::minimax,
-        board::{Board, PlayingPosition, Tile},
+        artificial_intelligence::{minimax, minimax_cancellable, minimax_defensive, Move},
+        board::{Board, PlayingPosition, Tile, TileTheme},
     },
-    input::Key,
+    input::{Key, KeyBindings},
+    network::Connection,
+    random,
     rendering::{Error, Renderer},
+    scores::Scoreboard,
+    settings::Settings,
 };
-use rand::Rng;
+#[cfg(test)]
+use std::cell::Cell;
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The longest the computer thinks about a move on a board larger than 3x3, whose games can't all be explored in time.
+const SEARCH_BUDGET: Duration = Duration::from_secs(2);
+
+/// A search for the best move running on another thread, so that the game stays responsive while the computer thinks. The search is stopped when this is dropped.
+struct BackgroundSearch {
+    /// Stops the search once set, which then returns the best move found so far.
+    cancel: Arc<AtomicBool>,
+    /// The time after which the search is stopped.
+    deadline: Instant,
+    result: Receiver<Move>,
+}
+
+impl BackgroundSearch {
+    /// Starts looking for the best move of the given player.
+    fn start(board: &Board, tile: Tile) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, result) = mpsc::channel();
+        let mut temp_board = board.clone();
+        let flag = cancel.clone();
+        // The random choices of the search follow those of the game, so that a seed still plays the same games.
+        let seed = random::gen_range(0, usize::MAX) as u64;
+        thread::spawn(move || {
+            random::seed(seed);
+            // The game may not wait for the move anymore.
+            let _ = sender.send(minimax_cancellable(&mut temp_board, tile, flag));
+        });
+        BackgroundSearch {
+            cancel,
+            deadline: Instant::now() + SEARCH_BUDGET,
+            result,
+        }
+    }
+}
+
+impl Drop for BackgroundSearch {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Finds the best moves of a computer player. Shared by the copies of its controller.
+#[derive(Clone, Default)]
+struct MoveSearch(Rc<RefCell<Option<BackgroundSearch>>>);
+
+impl MoveSearch {
+    /// Stops the search in progress, if any. Its move is thrown away.
+    fn cancel(&self) {
+        self.0.borrow_mut().take();
+    }
+
+    /// Indicates whether a search is going on in the background.
+    fn is_running(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    /// Finds the best move of the given player. It is found right away on a 3x3 board. On larger ones, whose games can't all be explored in time, a search runs in the background for at most `SEARCH_BUDGET`: returns `None` until it is over.
+    fn poll(&self, board: &Board, tile: Tile) -> Option<Move> {
+        if board.size() <= 3 {
+            let mut temp_board = board.clone();
+            return Some(minimax(&mut temp_board, tile));
+        }
+        let mut search = self.0.borrow_mut();
+        let current = search.get_or_insert_with(|| BackgroundSearch::start(board, tile));
+        if Instant::now() >= current.deadline {
+            current.cancel.store(true, Ordering::Relaxed);
+        }
+        match current.result.try_recv() {
+            Ok(m) => {
+                search.take();
+                Some(m)
+            }
+            Err(TryRecvError::Empty) => None,
+            // The search can't have ended without a move.
+            Err(TryRecvError::Disconnected) => {
+                search.take();
+                None
+            }
+        }
+    }
+}
 
 /// Represents a player.
 #[derive(Clone)]
 pub struct Player {
     /// The way this player will interact with the board.
     pub controller: Box<dyn PlayerController>,
-    /// The current count of wins for this player.
-    pub score: usize,
     /// The kind of tile that this player will place on the board.
     pub tile: Tile,
 }
@@ -22,22 +117,110 @@ pub struct Player {
 impl Player {
     /// Constructs a new player.
     pub fn new(controller: Box<dyn PlayerController>, tile: Tile) -> Self {
-        Player {
-            controller,
-            score: 0,
-            tile,
-        }
+        Player { controller, tile }
     }
 
-    /// Renders this player to the terminal.
-    pub fn render(&self, renderer: &Renderer) -> Result<(), Error> {
-        self.tile.render(renderer)?;
+    /// Renders this player and its number of wins to the terminal.
+    pub fn render(
+        &self,
+        renderer: &dyn Renderer,
+        theme: &TileTheme,
+        scoreboard: &Scoreboard,
+    ) -> Result<(), Error> {
+        self.tile.render(renderer, theme)?;
         renderer.write(": ")?;
-        renderer.write(&self.score.to_string())?;
+        renderer.write(&scoreboard.get_wins(self.tile).to_string())?;
         Ok(())
     }
 }
 
+/// The kinds of opponent the user can play against.
+#[derive(Clone, Copy, PartialEq)]
+pub enum OpponentKind {
+    /// A computer playing the best move more or less often, as set by `Settings::computer_difficulty`.
+    AdjustableComputer,
+    /// A computer playing for a draw, leaving the winning to its opponent.
+    DefensiveComputer,
+    /// A computer playing random spots.
+    EasyComputer,
+    /// Another human, sharing the same keyboard.
+    Friend,
+    /// A computer taking and blocking obvious wins.
+    MediumComputer,
+    /// A computer playing perfectly.
+    UnbeatableComputer,
+}
+
+impl OpponentKind {
+    /// Constructs the controller of this kind of opponent, which will play the given kind of tile. A human opponent moves as the settings tell.
+    pub fn create_controller(self, tile: Tile, settings: &Settings) -> Box<dyn PlayerController> {
+        match self {
+            OpponentKind::AdjustableComputer => Box::new(ComputerPlayerController::new(
+                tile,
+                settings.computer_difficulty,
+            )),
+            OpponentKind::DefensiveComputer => {
+                Box::new(DefensiveComputerPlayerController::new(tile))
+            }
+            OpponentKind::EasyComputer => {
+                let mut controller = BasicComputerPlayerController::new(tile);
+                controller.avoid_immediate_loss = settings.cautious_easy_computer;
+                Box::new(controller)
+            }
+            OpponentKind::Friend => Box::new(HumanPlayerController::with_settings(settings)),
+            OpponentKind::MediumComputer => Box::new(MediumComputerPlayerController::new(tile)),
+            OpponentKind::UnbeatableComputer => {
+                Box::new(UnbeatableComputerPlayerController::new(tile))
+            }
+        }
+    }
+
+    /// Gets the kind of opponent with the given name, as typed on the command line: `friend`, `easy`, `medium`, `unbeatable`, `sparring` or `adjustable`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "adjustable" => Some(OpponentKind::AdjustableComputer),
+            "easy" => Some(OpponentKind::EasyComputer),
+            "friend" => Some(OpponentKind::Friend),
+            "medium" => Some(OpponentKind::MediumComputer),
+            "sparring" => Some(OpponentKind::DefensiveComputer),
+            "unbeatable" => Some(OpponentKind::UnbeatableComputer),
+            _ => None,
+        }
+    }
+
+    /// Gets a short description of this kind of opponent, to be shown to the user.
+    pub fn get_description(self) -> &'static str {
+        match self {
+            OpponentKind::AdjustableComputer => "the computer (adjustable)",
+            OpponentKind::DefensiveComputer => "the computer (sparring)",
+            OpponentKind::EasyComputer => "the computer (easy)",
+            OpponentKind::Friend => "a friend",
+            OpponentKind::MediumComputer => "the computer (medium)",
+            OpponentKind::UnbeatableComputer => "the computer (unbeatable)",
+        }
+    }
+
+    /// Gets an explanation of how this kind of opponent plays, to be shown to the user.
+    pub fn get_help(self) -> &'static str {
+        match self {
+            OpponentKind::AdjustableComputer => {
+                "Plays the best move more often as its difficulty grows, from 0 (any free spot) to 10 (never a mistake). The difficulty is set in the settings."
+            }
+            OpponentKind::DefensiveComputer => {
+                "Never loses if it can help it, but never takes a win either: finding the winning line is up to you."
+            }
+            OpponentKind::EasyComputer => "Plays any free spot, without thinking.",
+            OpponentKind::Friend => "Take turns on the same keyboard.",
+            OpponentKind::MediumComputer => {
+                "Completes its lines and blocks yours when one tile is missing, otherwise plays any free spot."
+            }
+            OpponentKind::UnbeatableComputer => {
+                "Looks ahead at every possible game and never makes a mistake: the best you can get is a draw."
+            }
+        }
+    }
+}
+
 /// Describes all actions that can be performed by a player.
 pub enum PlayerAction {
     /// Moving the cursor.
@@ -50,27 +233,96 @@ pub enum PlayerAction {
 
 /// A generic player controller.
 pub trait PlayerController {
-    fn box_clone(&self) -> Box<PlayerController>;
+    fn box_clone(&self) -> Box<dyn PlayerController>;
+
+    /// Called when the turn of this player is taken back before it played, such as when moves are undone while a computer thinks.
+    fn cancel_turn(&self) {}
 
     /// Handles a key press.
     fn handle_key_press(&self, board: &Board, key: Key) -> PlayerAction;
 
-    /// Called when player's turn starts.
+    /// Indicates whether this player left the game, such as a remote player whose connection was lost. The game can't go on then.
+    fn has_left(&self) -> bool {
+        false
+    }
+
+    /// Indicates whether this player plays on its own, without any human interaction. The turns of an automated player are played by calling `start_turn` once a short delay is over, without waiting for a key press, and again after each delay until it returns the move to play.
+    fn is_automated(&self) -> bool {
+        false
+    }
+
+    /// Indicates whether this player plays on another computer. Only the moves are shared with it, so what would change the game on this side only, such as undoing a move, is refused.
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    /// Called when another player played the given spot.
+    fn notify_move(&self, _position: PlayingPosition) {}
+
+    /// Called when player's turn starts. For a player that is not automated, this is called right away and any move it returns is applied immediately.
     fn start_turn(&self, board: &Board) -> PlayerAction;
 }
 
-impl Clone for Box<PlayerController> {
-    fn clone(&self) -> Box<PlayerController> {
+impl Clone for Box<dyn PlayerController> {
+    fn clone(&self) -> Box<dyn PlayerController> {
         self.box_clone()
     }
 }
 
+/// Looks for a spot that would make the given tile win right away.
+fn find_winning_spot(board: &Board, tile: Tile) -> Option<PlayingPosition> {
+    let mut temp_board = board.clone();
+    for spot in board.get_available_spots() {
+        if let Ok(Some(_)) = temp_board
+            .set(spot.0, spot.1, tile)
+            .map(|r| r.winner().cloned())
+        {
+            return Some(spot);
+        }
+        temp_board.reset(spot.0, spot.1);
+    }
+    None
+}
+
+/// Plays one of the given spots, picked at random. Does nothing when there are none, on a full board.
+fn play_random_spot(spots: &[PlayingPosition]) -> PlayerAction {
+    if spots.is_empty() {
+        return PlayerAction::None;
+    }
+    PlayerAction::Play(spots[random::gen_range(0, spots.len())])
+}
+
 /// A basic computer player that will play a random spot each turn.
 #[derive(Clone)]
-pub struct BasicComputerPlayerController {}
+pub struct BasicComputerPlayerController {
+    /// Indicates whether the spots that would let the opponent win on the next turn are left out, as long as there are other spots to play.
+    pub avoid_immediate_loss: bool,
+    tile: Tile,
+}
+
+impl BasicComputerPlayerController {
+    /// Constructs a new basic computer player which will play the given kind of tile, any free spot.
+    pub fn new(tile: Tile) -> Self {
+        BasicComputerPlayerController {
+            avoid_immediate_loss: false,
+            tile,
+        }
+    }
+
+    /// Indicates whether playing the given spot lets the opponent win on the next turn.
+    fn loses_immediately(&self, board: &Board, spot: PlayingPosition) -> bool {
+        let opponent = self.tile.opponent();
+        match board.with_move(spot, self.tile) {
+            Ok((temp_board, result)) if !result.is_finished() => {
+                find_winning_spot(&temp_board, opponent).is_some()
+            }
+            _ => false,
+        }
+    }
+}
 
 impl PlayerController for BasicComputerPlayerController {
-    fn box_clone(&self) -> Box<PlayerController> {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
         Box::new((*self).clone())
     }
 
@@ -79,31 +331,209 @@ impl PlayerController for BasicComputerPlayerController {
         PlayerAction::None
     }
 
+    fn is_automated(&self) -> bool {
+        true
+    }
+
     fn start_turn(&self, board: &Board) -> PlayerAction {
-        let spots = board.get_available_spots();
-        let mut rng = rand::thread_rng();
-        // Pick a random (but empty) spot.
-        PlayerAction::Play(spots[rng.gen_range(0, spots.len())])
+        let mut spots = board.get_available_spots();
+        if self.avoid_immediate_loss {
+            let safe_spots = spots
+                .iter()
+                .filter(|spot| !self.loses_immediately(board, **spot))
+                .cloned()
+                .collect::<Vec<PlayingPosition>>();
+            if !safe_spots.is_empty() {
+                spots = safe_spots;
+            }
+        }
+        play_random_spot(&spots)
+    }
+}
+
+/// The difficulty of a `ComputerPlayerController` that always plays the best move.
+pub const MAX_DIFFICULTY: u8 = 10;
+
+/// A computer player of adjustable difficulty, from 0 to `MAX_DIFFICULTY`.
+///
+/// Each turn, it plays the best move (found by minimax) with a probability of `difficulty / MAX_DIFFICULTY`, and a random spot otherwise: difficulty 0 always plays at random, like `BasicComputerPlayerController`, and `MAX_DIFFICULTY` never makes a mistake, like `UnbeatableComputerPlayerController`. The probability grows linearly in between, 5 picking the best move every other turn on average.
+#[derive(Clone)]
+pub struct ComputerPlayerController {
+    difficulty: u8,
+    search: MoveSearch,
+    tile: Tile,
+}
+
+impl ComputerPlayerController {
+    /// Constructs a new computer player of the given difficulty, capped to `MAX_DIFFICULTY`, which will play the given kind of tile.
+    pub fn new(tile: Tile, difficulty: u8) -> Self {
+        ComputerPlayerController {
+            difficulty: difficulty.min(MAX_DIFFICULTY),
+            search: MoveSearch::default(),
+            tile,
+        }
+    }
+}
+
+impl PlayerController for ComputerPlayerController {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
+        Box::new((*self).clone())
+    }
+
+    fn cancel_turn(&self) {
+        self.search.cancel();
+    }
+
+    fn handle_key_press(&self, _: &Board, _: Key) -> PlayerAction {
+        // Never respond to key presses.
+        PlayerAction::None
+    }
+
+    fn is_automated(&self) -> bool {
+        true
+    }
+
+    fn start_turn(&self, board: &Board) -> PlayerAction {
+        // The extreme difficulties do not draw lots, so that difficulty 0 makes the same random choices as the basic player. Lots are drawn once per turn, not again while the best move is searched.
+        let plays_best = self.search.is_running()
+            || match self.difficulty {
+                0 => false,
+                MAX_DIFFICULTY => true,
+                d => random::gen_range(0, MAX_DIFFICULTY as usize) < d as usize,
+            };
+        if plays_best && !board.is_full() {
+            return match self.search.poll(board, self.tile) {
+                Some(m) => PlayerAction::Play(m.pos),
+                None => PlayerAction::None,
+            };
+        }
+        play_random_spot(&board.get_available_spots())
+    }
+}
+
+/// A computer player that plays for a draw: it never loses if it can help it, but never takes a win either, unless every other move loses. Used for sparring, so that the human has to find the winning line on their own.
+#[derive(Clone)]
+pub struct DefensiveComputerPlayerController {
+    tile: Tile,
+}
+
+impl DefensiveComputerPlayerController {
+    /// Constructs a new defensive computer player which will play the given kind of tile.
+    pub fn new(tile: Tile) -> Self {
+        DefensiveComputerPlayerController { tile }
+    }
+}
+
+impl PlayerController for DefensiveComputerPlayerController {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
+        Box::new((*self).clone())
+    }
+
+    fn handle_key_press(&self, _: &Board, _: Key) -> PlayerAction {
+        // Never respond to key presses.
+        PlayerAction::None
+    }
+
+    fn is_automated(&self) -> bool {
+        true
+    }
+
+    fn start_turn(&self, board: &Board) -> PlayerAction {
+        if board.is_full() {
+            return PlayerAction::None;
+        }
+        let mut temp_board = board.clone();
+        PlayerAction::Play(minimax_defensive(&mut temp_board, self.tile).pos)
     }
 }
 
 /// A human controlled player.
+///
+/// The cursor is moved and the tile is placed with the keys given by its bindings, by default the arrow keys (or the vim-style h, j, k and l keys) and enter. On a classic 3x3 board, digits that are not bound also play a spot directly, following the layout of a numeric keypad:
+///
+/// ```text
+/// 7|8|9
+/// -+-+-
+/// 4|5|6
+/// -+-+-
+/// 1|2|3
+/// ```
 #[derive(Clone)]
-pub struct HumanPlayerController {}
+pub struct HumanPlayerController {
+    key_bindings: KeyBindings,
+    /// Indicates whether moving the cursor past an edge of the board brings it to the opposite edge, rather than keeping it in place.
+    pub wrap_around: bool,
+}
+
+impl HumanPlayerController {
+    /// Constructs a new human player which will use the given key bindings.
+    pub fn new(key_bindings: KeyBindings) -> Self {
+        HumanPlayerController {
+            key_bindings,
+            wrap_around: false,
+        }
+    }
+
+    /// Constructs a new human player using the key bindings of the settings, and wrapping around the board if they ask for it.
+    pub fn with_settings(settings: &Settings) -> Self {
+        HumanPlayerController {
+            wrap_around: settings.wrap_around,
+            ..HumanPlayerController::new(settings.key_bindings.clone())
+        }
+    }
+
+    /// Gets the coordinate one step away from the given one, forward or backward, on a board of the given size. Returns `None` past an edge, unless wrapping around.
+    fn step(&self, coordinate: u8, forward: bool, size: u8) -> Option<u8> {
+        match (forward, self.wrap_around) {
+            (true, _) if coordinate + 1 < size => Some(coordinate + 1),
+            (true, true) => Some(0),
+            (false, _) if coordinate > 0 => Some(coordinate - 1),
+            (false, true) => Some(size - 1),
+            _ => None,
+        }
+    }
+
+    /// Maps a digit to a spot of a 3x3 board, following the layout of a numeric keypad.
+    fn numpad_position(c: char) -> Option<PlayingPosition> {
+        let digit = c.to_digit(10).filter(|d| *d > 0)? as u8 - 1;
+        Some((digit % 3, 2 - digit / 3))
+    }
+}
 
 impl PlayerController for HumanPlayerController {
-    fn box_clone(&self) -> Box<PlayerController> {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
         Box::new((*self).clone())
     }
 
     fn handle_key_press(&self, board: &Board, key: Key) -> PlayerAction {
         let pos = board.playing_position;
+        let size = board.size();
+        let bindings = &self.key_bindings;
         match key {
-            Key::Char('\n') if board.is_empty(pos.0, pos.1) => PlayerAction::Play(pos),
-            Key::Down if pos.1 < 2 => PlayerAction::Move((pos.0, pos.1 + 1)),
-            Key::Left if pos.0 > 0 => PlayerAction::Move((pos.0 - 1, pos.1)),
-            Key::Right if pos.0 < 2 => PlayerAction::Move((pos.0 + 1, pos.1)),
-            Key::Up if pos.1 > 0 => PlayerAction::Move((pos.0, pos.1 - 1)),
+            k if bindings.confirm.contains(&k) => {
+                if board.is_empty(pos.0, pos.1) {
+                    PlayerAction::Play(pos)
+                } else {
+                    PlayerAction::None
+                }
+            }
+            // A movement key does nothing past an edge, unless wrapping around.
+            k if bindings.down.contains(&k) => self
+                .step(pos.1, true, size)
+                .map_or(PlayerAction::None, |y| PlayerAction::Move((pos.0, y))),
+            k if bindings.left.contains(&k) => self
+                .step(pos.0, false, size)
+                .map_or(PlayerAction::None, |x| PlayerAction::Move((x, pos.1))),
+            k if bindings.right.contains(&k) => self
+                .step(pos.0, true, size)
+                .map_or(PlayerAction::None, |x| PlayerAction::Move((x, pos.1))),
+            k if bindings.up.contains(&k) => self
+                .step(pos.1, false, size)
+                .map_or(PlayerAction::None, |y| PlayerAction::Move((pos.0, y))),
+            Key::Char(c) if board.size() == 3 => match HumanPlayerController::numpad_position(c) {
+                Some(spot) if board.is_empty(spot.0, spot.1) => PlayerAction::Play(spot),
+                _ => PlayerAction::None,
+            },
             _ => PlayerAction::None,
         }
     }
@@ -114,23 +544,543 @@ impl PlayerController for HumanPlayerController {
     }
 }
 
+/// A computer player that takes an immediate win, or else blocks an immediate win of its opponent, or else plays a random spot.
+#[derive(Clone)]
+pub struct MediumComputerPlayerController {
+    tile: Tile,
+}
+
+impl MediumComputerPlayerController {
+    /// Constructs a new medium computer player which will play the given kind of tile.
+    pub fn new(tile: Tile) -> Self {
+        MediumComputerPlayerController { tile }
+    }
+}
+
+impl PlayerController for MediumComputerPlayerController {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
+        Box::new((*self).clone())
+    }
+
+    fn handle_key_press(&self, _: &Board, _: Key) -> PlayerAction {
+        // Never respond to key presses.
+        PlayerAction::None
+    }
+
+    fn is_automated(&self) -> bool {
+        true
+    }
+
+    fn start_turn(&self, board: &Board) -> PlayerAction {
+        let opponent = self.tile.opponent();
+        if let Some(spot) =
+            find_winning_spot(board, self.tile).or_else(|| find_winning_spot(board, opponent))
+        {
+            return PlayerAction::Play(spot);
+        }
+        play_random_spot(&board.get_available_spots())
+    }
+}
+
+/// A player on another computer, whose moves are received over the network. The moves of the other player are sent to it as they are played.
+#[derive(Clone)]
+pub struct NetworkPlayerController {
+    connection: Rc<Connection>,
+}
+
+impl NetworkPlayerController {
+    /// Constructs a player playing the moves received over the given connection.
+    pub fn new(connection: Rc<Connection>) -> Self {
+        NetworkPlayerController { connection }
+    }
+}
+
+impl PlayerController for NetworkPlayerController {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
+        Box::new((*self).clone())
+    }
+
+    fn handle_key_press(&self, _: &Board, _: Key) -> PlayerAction {
+        // The remote player uses its own keyboard.
+        PlayerAction::None
+    }
+
+    fn has_left(&self) -> bool {
+        self.connection.has_left()
+    }
+
+    fn is_automated(&self) -> bool {
+        true
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    fn notify_move(&self, position: PlayingPosition) {
+        // A lost connection is noticed when waiting for the next move.
+        let _ = self.connection.send_move(position);
+    }
+
+    fn start_turn(&self, _: &Board) -> PlayerAction {
+        // Wait for the next turn if the move did not arrive yet.
+        match self.connection.receive_move() {
+            Some(position) => PlayerAction::Play(position),
+            None => PlayerAction::None,
+        }
+    }
+}
+
+/// A player that plays a fixed list of spots, in order, one per turn. Used to test full games.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct ScriptedPlayerController {
+    moves: Vec<PlayingPosition>,
+    /// The index of the next move to play.
+    next_move: Cell<usize>,
+}
+
+#[cfg(test)]
+impl ScriptedPlayerController {
+    /// Constructs a new scripted player which will play the given spots.
+    pub fn new(moves: Vec<PlayingPosition>) -> Self {
+        ScriptedPlayerController {
+            moves,
+            next_move: Cell::new(0),
+        }
+    }
+}
+
+#[cfg(test)]
+impl PlayerController for ScriptedPlayerController {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
+        Box::new((*self).clone())
+    }
+
+    fn handle_key_press(&self, _: &Board, _: Key) -> PlayerAction {
+        // Never respond to key presses.
+        PlayerAction::None
+    }
+
+    fn is_automated(&self) -> bool {
+        true
+    }
+
+    fn start_turn(&self, board: &Board) -> PlayerAction {
+        let index = self.next_move.get();
+        match self.moves.get(index) {
+            Some(&(x, y)) if board.try_get(x, y) == Some(&Tile::Empty) => {
+                self.next_move.set(index + 1);
+                PlayerAction::Play((x, y))
+            }
+            // The script is over, or its next move is illegal.
+            _ => PlayerAction::None,
+        }
+    }
+}
+
 /// A computer player that uses a minimax algorithm.
 #[derive(Clone)]
-pub struct UnbeatableComputerPlayerController {}
+pub struct UnbeatableComputerPlayerController {
+    search: MoveSearch,
+    tile: Tile,
+}
+
+impl UnbeatableComputerPlayerController {
+    /// Constructs a new unbeatable computer player which will play the given kind of tile.
+    pub fn new(tile: Tile) -> Self {
+        UnbeatableComputerPlayerController {
+            search: MoveSearch::default(),
+            tile,
+        }
+    }
+}
 
 impl PlayerController for UnbeatableComputerPlayerController {
-    fn box_clone(&self) -> Box<PlayerController> {
+    fn box_clone(&self) -> Box<dyn PlayerController> {
         Box::new((*self).clone())
     }
 
+    fn cancel_turn(&self) {
+        self.search.cancel();
+    }
+
     fn handle_key_press(&self, _: &Board, _: Key) -> PlayerAction {
         // Never respond to key presses.
         PlayerAction::None
     }
 
+    fn is_automated(&self) -> bool {
+        true
+    }
+
     fn start_turn(&self, board: &Board) -> PlayerAction {
-        let mut temp_board = board.clone();
-        // Play the best available move.
-        PlayerAction::Play(minimax(&mut temp_board, Tile::X).pos)
+        if board.is_full() {
+            return PlayerAction::None;
+        }
+        // Play the best available move, once found.
+        match self.search.poll(board, self.tile) {
+            Some(m) => PlayerAction::Play(m.pos),
+            None => PlayerAction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board on which every spot is taken, without a winner.
+    fn full_board() -> Board {
+        let mut board = Board::new();
+        for (i, &(x, y)) in [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (1, 1),
+            (0, 1),
+            (2, 1),
+            (1, 2),
+            (0, 2),
+            (2, 2),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let tile = if i % 2 == 0 { Tile::X } else { Tile::O };
+            let _ = board.set(x, y, tile);
+        }
+        board
+    }
+
+    /// Plays every possible move of x against the given computer placing o, and checks that o never loses.
+    fn assert_never_loses_as_o(board: &Board, computer: &dyn PlayerController) {
+        for (x, y) in board.get_available_spots() {
+            let (board, result) = board.with_move((x, y), Tile::X).ok().unwrap();
+            if result.is_finished() {
+                assert!(result.winner().is_none());
+                continue;
+            }
+            let spot = match computer.start_turn(&board) {
+                PlayerAction::Play(spot) => spot,
+                _ => panic!("the computer passed on a board with free spots"),
+            };
+            let (board, result) = board.with_move(spot, Tile::O).ok().unwrap();
+            if !result.is_finished() {
+                assert_never_loses_as_o(&board, computer);
+            }
+        }
+    }
+
+    #[test]
+    fn defensive_computer_never_loses() {
+        assert_never_loses_as_o(
+            &Board::new(),
+            &DefensiveComputerPlayerController::new(Tile::O),
+        );
+    }
+
+    #[test]
+    fn defensive_computer_prefers_a_draw_to_a_win() {
+        // o can win on the top right corner, but blocking the row of x on the right keeps the game heading to a draw.
+        let mut board = Board::new();
+        for &(x, y, tile) in [
+            (0, 0, Tile::O),
+            (1, 0, Tile::O),
+            (1, 1, Tile::X),
+            (2, 2, Tile::X),
+            (0, 1, Tile::X),
+        ]
+        .iter()
+        {
+            let _ = board.set(x, y, tile);
+        }
+        let defensive = DefensiveComputerPlayerController::new(Tile::O);
+        for _ in 0..10 {
+            match defensive.start_turn(&board) {
+                PlayerAction::Play(spot) => assert!(spot == (2, 1)),
+                _ => panic!("the defensive player passed on a board with free spots"),
+            }
+        }
+    }
+
+    /// Plays a whole game between two computers, x starting, and returns the spots played.
+    fn play_computers(x: &dyn PlayerController, o: &dyn PlayerController) -> Vec<PlayingPosition> {
+        let mut board = Board::new();
+        let mut moves = Vec::new();
+        for (turn, tile) in [Tile::X, Tile::O].iter().cycle().enumerate() {
+            let controller = if turn % 2 == 0 { x } else { o };
+            let spot = match controller.start_turn(&board) {
+                PlayerAction::Play(spot) => spot,
+                _ => break,
+            };
+            moves.push(spot);
+            if board.set(spot.0, spot.1, *tile).ok().unwrap().is_finished() {
+                break;
+            }
+        }
+        moves
+    }
+
+    #[test]
+    fn computer_of_difficulty_0_plays_like_the_basic_computer() {
+        let opponent = UnbeatableComputerPlayerController::new(Tile::O);
+        random::seed(42);
+        let basic = play_computers(&BasicComputerPlayerController::new(Tile::X), &opponent);
+        random::seed(42);
+        let computer = play_computers(&ComputerPlayerController::new(Tile::X, 0), &opponent);
+        assert_eq!(basic, computer);
+    }
+
+    #[test]
+    fn computer_of_max_difficulty_never_loses() {
+        for seed in 0..10 {
+            random::seed(seed);
+            let x = ComputerPlayerController::new(Tile::X, MAX_DIFFICULTY + 1);
+            assert_eq!(x.difficulty, MAX_DIFFICULTY);
+            let mut board = Board::new();
+            for (i, &spot) in play_computers(&x, &BasicComputerPlayerController::new(Tile::O))
+                .iter()
+                .enumerate()
+            {
+                let _ = board.set(spot.0, spot.1, if i % 2 == 0 { Tile::X } else { Tile::O });
+            }
+            assert!(board.status().winner() != Some(&Tile::O));
+        }
+    }
+
+    #[test]
+    fn computers_do_nothing_on_a_full_board() {
+        let board = full_board();
+        assert!(board.is_full());
+        let controllers: Vec<Box<dyn PlayerController>> = vec![
+            Box::new(BasicComputerPlayerController::new(Tile::O)),
+            Box::new(ComputerPlayerController::new(Tile::O, 0)),
+            Box::new(ComputerPlayerController::new(Tile::O, MAX_DIFFICULTY)),
+            Box::new(DefensiveComputerPlayerController::new(Tile::O)),
+            Box::new(MediumComputerPlayerController::new(Tile::O)),
+            Box::new(UnbeatableComputerPlayerController::new(Tile::O)),
+        ];
+        for controller in controllers {
+            assert!(matches!(controller.start_turn(&board), PlayerAction::None));
+        }
+    }
+
+    #[test]
+    fn unbeatable_computer_never_loses_as_o() {
+        assert_never_loses_as_o(
+            &Board::new(),
+            &UnbeatableComputerPlayerController::new(Tile::O),
+        );
+    }
+
+    /// Builds a 3x3 board on which the given spots are played in turn, x first.
+    fn board_with_moves(moves: &[PlayingPosition]) -> Board {
+        let mut board = Board::new();
+        for (i, spot) in moves.iter().enumerate() {
+            let _ = board.set(spot.0, spot.1, if i % 2 == 0 { Tile::X } else { Tile::O });
+        }
+        board
+    }
+
+    #[test]
+    fn medium_computer_wins_or_else_blocks() {
+        let medium = MediumComputerPlayerController::new(Tile::O);
+        // Both x and o can complete a line: o takes its win.
+        let board = board_with_moves(&[(1, 0), (0, 1), (2, 0), (0, 2), (2, 2)]);
+        for _ in 0..10 {
+            assert!(matches!(
+                medium.start_turn(&board),
+                PlayerAction::Play((0, 0))
+            ));
+        }
+        // Only x can complete a line: o blocks it.
+        let board = board_with_moves(&[(0, 0), (1, 1), (1, 0)]);
+        for _ in 0..10 {
+            assert!(matches!(
+                medium.start_turn(&board),
+                PlayerAction::Play((2, 0))
+            ));
+        }
+    }
+
+    #[test]
+    fn digits_play_the_spots_of_a_numeric_keypad() {
+        let human = HumanPlayerController::new(KeyBindings::default());
+        let board = board_with_moves(&[(1, 1)]);
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('1')),
+            PlayerAction::Play((0, 2))
+        ));
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('9')),
+            PlayerAction::Play((2, 0))
+        ));
+        // The center is taken.
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('5')),
+            PlayerAction::None
+        ));
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('0')),
+            PlayerAction::None
+        ));
+        assert!(matches!(
+            human.handle_key_press(&Board::with_size(4), Key::Char('1')),
+            PlayerAction::None
+        ));
+    }
+
+    #[test]
+    fn vim_keys_move_the_cursor_like_the_arrows() {
+        let human = HumanPlayerController::new(KeyBindings::default());
+        let mut board = Board::new();
+        board.playing_position = (1, 1);
+        for (vim, arrow, expected) in [
+            ('h', Key::Left, (0, 1)),
+            ('j', Key::Down, (1, 2)),
+            ('k', Key::Up, (1, 0)),
+            ('l', Key::Right, (2, 1)),
+        ] {
+            assert!(
+                matches!(human.handle_key_press(&board, Key::Char(vim)), PlayerAction::Move(p) if p == expected)
+            );
+            assert!(
+                matches!(human.handle_key_press(&board, arrow), PlayerAction::Move(p) if p == expected)
+            );
+        }
+        // The cursor stays on the board.
+        board.playing_position = (0, 0);
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('h')),
+            PlayerAction::None
+        ));
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('k')),
+            PlayerAction::None
+        ));
+    }
+
+    #[test]
+    fn remapped_keys_drive_the_human_player() {
+        let human = HumanPlayerController::new(KeyBindings {
+            confirm: vec![Key::Char(' ')],
+            ..KeyBindings::default()
+        });
+        let board = Board::new();
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char(' ')),
+            PlayerAction::Play((1, 1))
+        ));
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('\n')),
+            PlayerAction::None
+        ));
+        // With the keypad bindings, 5 confirms instead of playing the center, and 8 moves up.
+        let human = HumanPlayerController::new(KeyBindings::keypad());
+        let mut board = board_with_moves(&[(0, 0)]);
+        board.playing_position = (2, 2);
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('5')),
+            PlayerAction::Play((2, 2))
+        ));
+        assert!(matches!(
+            human.handle_key_press(&board, Key::Char('8')),
+            PlayerAction::Move((2, 1))
+        ));
+    }
+
+    #[test]
+    fn basic_computer_can_avoid_losing_right_away() {
+        let mut basic = BasicComputerPlayerController::new(Tile::O);
+        basic.avoid_immediate_loss = true;
+        // Any spot but the top right corner lets x complete the top row.
+        let board = board_with_moves(&[(0, 0), (1, 1), (1, 0)]);
+        for seed in 0..10 {
+            random::seed(seed);
+            assert!(matches!(
+                basic.start_turn(&board),
+                PlayerAction::Play((2, 0))
+            ));
+        }
+        // x threatens two lines, so every spot loses: one is still played.
+        let board = board_with_moves(&[(0, 0), (1, 1), (1, 0), (2, 2), (0, 1)]);
+        assert!(matches!(basic.start_turn(&board), PlayerAction::Play(_)));
+    }
+
+    #[test]
+    fn cursor_stops_or_wraps_around_at_each_edge() {
+        let mut human = HumanPlayerController::new(KeyBindings::default());
+        for &size in [3, 4].iter() {
+            let last = size - 1;
+            let mut board = Board::with_size(size);
+            // From each corner, the keys leading off the board.
+            for (position, key, wrapped) in [
+                ((0, 0), Key::Left, (last, 0)),
+                ((0, 0), Key::Up, (0, last)),
+                ((last, last), Key::Right, (0, last)),
+                ((last, last), Key::Down, (last, 0)),
+            ] {
+                board.playing_position = position;
+                human.wrap_around = false;
+                assert!(matches!(
+                    human.handle_key_press(&board, key.clone()),
+                    PlayerAction::None
+                ));
+                human.wrap_around = true;
+                assert!(
+                    matches!(human.handle_key_press(&board, key.clone()), PlayerAction::Move(p) if p == wrapped)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn undoing_stops_the_search_of_the_computer() {
+        let computer = UnbeatableComputerPlayerController::new(Tile::X);
+        // The search of a large board goes on in the background.
+        assert!(matches!(
+            computer.start_turn(&Board::with_size(4)),
+            PlayerAction::None
+        ));
+        let cancel = computer.search.0.borrow().as_ref().unwrap().cancel.clone();
+        assert!(!cancel.load(Ordering::Relaxed));
+        computer.cancel_turn();
+        assert!(cancel.load(Ordering::Relaxed));
+        assert!(computer.search.0.borrow().is_none());
+    }
+
+    #[test]
+    fn adjustable_computer_plays_at_the_difficulty_of_the_settings() {
+        let opponent = UnbeatableComputerPlayerController::new(Tile::O);
+        let settings = Settings {
+            computer_difficulty: 0,
+            ..Settings::default()
+        };
+        let adjustable = OpponentKind::from_name("adjustable")
+            .unwrap()
+            .create_controller(Tile::X, &settings);
+        random::seed(7);
+        let basic = play_computers(&BasicComputerPlayerController::new(Tile::X), &opponent);
+        random::seed(7);
+        assert_eq!(play_computers(adjustable.as_ref(), &opponent), basic);
+    }
+
+    #[test]
+    fn cautious_easy_computer_blocks_a_loss_on_the_next_move() {
+        let settings = Settings {
+            cautious_easy_computer: true,
+            ..Settings::default()
+        };
+        let easy = OpponentKind::EasyComputer.create_controller(Tile::O, &settings);
+        // Any spot but the top right corner lets x complete the top row.
+        let board = board_with_moves(&[(0, 0), (1, 1), (1, 0)]);
+        for seed in 0..10 {
+            random::seed(seed);
+            assert!(matches!(
+                easy.start_turn(&board),
+                PlayerAction::Play((2, 0))
+            ));
+        }
     }
 }
@@ -1,4 +1,9 @@
-use crossterm::{KeyEvent, RawScreen, TerminalInput};
+use crate::rendering::{CursorPosition, ScreenSize};
+use crossterm::{KeyEvent, MouseButton, MouseEvent, RawScreen, Terminal, TerminalInput};
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
 
 /// The keyboard keys.
 #[derive(Clone, PartialEq)]
@@ -24,57 +29,244 @@ pub enum Key {
 pub enum InputEvent {
     Key(Key),
     Line(String),
+    /// A click of the left mouse button, on the given cell of the screen, `(0, 0)` being the top left one.
+    Mouse(CursorPosition),
+    /// The terminal was resized. It is drawn again at its new size.
+    Resize,
+}
+
+/// The keys that trigger each action of a game. Every action can be bound to several keys.
+#[derive(Clone, PartialEq)]
+pub struct KeyBindings {
+    /// Leaves the game, going back to the choice of the opponent.
+    pub back: Vec<Key>,
+    /// Places a tile on the current spot.
+    pub confirm: Vec<Key>,
+    /// Moves the cursor one spot down.
+    pub down: Vec<Key>,
+    /// Moves the cursor one spot left.
+    pub left: Vec<Key>,
+    /// Leaves the game.
+    pub quit: Vec<Key>,
+    /// Moves the cursor one spot right.
+    pub right: Vec<Key>,
+    /// Reverts the last move.
+    pub undo: Vec<Key>,
+    /// Moves the cursor one spot up.
+    pub up: Vec<Key>,
+}
+
+impl KeyBindings {
+    /// Constructs bindings moving the cursor with the digits of a numeric keypad, and confirming with 5.
+    pub fn keypad() -> Self {
+        KeyBindings {
+            confirm: vec![Key::Char('5'), Key::Char('\n')],
+            down: vec![Key::Char('2')],
+            left: vec![Key::Char('4')],
+            right: vec![Key::Char('6')],
+            up: vec![Key::Char('8')],
+            ..KeyBindings::default()
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    /// The arrow keys (or the vim-style h, j, k and l keys) to move, enter to confirm, b to go back, escape to quit and backspace or u to undo.
+    fn default() -> Self {
+        KeyBindings {
+            back: vec![Key::Char('b')],
+            confirm: vec![Key::Char('\n')],
+            down: vec![Key::Down, Key::Char('j')],
+            left: vec![Key::Left, Key::Char('h')],
+            quit: vec![Key::Escape],
+            right: vec![Key::Right, Key::Char('l')],
+            undo: vec![Key::Backspace, Key::Char('u')],
+            up: vec![Key::Up, Key::Char('k')],
+        }
+    }
 }
 
 /// The kind of input that a given state handles.
+#[derive(Clone, Copy, PartialEq)]
 pub enum InputMode {
     Key,
+    /// Keyboard keys as well as mouse clicks.
+    KeyAndMouse,
     Line,
 }
 
 /// A generic input reader.
 pub trait InputReader {
+    /// Waits for a keyboard key, as well as a click of the left mouse button in `InputMode::KeyAndMouse`, for at most the given duration. Returns `None` if nothing happened in time. A change of the terminal size while waiting is returned as an `InputEvent::Resize` as well. Lines can't be read with a timeout, so `InputMode::Line` only waits for a key.
+    fn poll_key(&self, mode: InputMode, timeout: Duration) -> Option<InputEvent>;
+
     /// Emits input events based on the input mode.
     fn read_input(&self, mode: InputMode) -> Result<InputEvent, std::io::Error> {
         match mode {
-            InputMode::Key => Ok(InputEvent::Key(self.read_key())),
-            InputMode::Line => self.read_line().map(|l| InputEvent::Line(l)),
+            InputMode::Key => Ok(self.read_key()),
+            InputMode::KeyAndMouse => Ok(self.read_key_or_click()),
+            InputMode::Line => self.read_line().map(InputEvent::Line),
         }
     }
 
-    /// Reads a keyboard key.
-    fn read_key(&self) -> Key;
+    /// Reads a keyboard key, or a change of the terminal size, whichever comes first.
+    fn read_key(&self) -> InputEvent;
+
+    /// Reads a keyboard key, a click of the left mouse button or a change of the terminal size, whichever comes first.
+    fn read_key_or_click(&self) -> InputEvent;
 
     /// Reads an entire line of text.
     fn read_line(&self) -> Result<String, std::io::Error>;
 }
 
+/// How often the size of the terminal is checked while waiting for a key.
+#[cfg(unix)]
+const RESIZE_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often the events read in the background are checked while waiting for a key with a timeout.
+#[cfg(not(unix))]
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Converts an event read by crossterm, keeping only the keys and, if `mouse` is set, the clicks of the left button.
+fn convert_event(event: crossterm::InputEvent, mouse: bool) -> Option<InputEvent> {
+    match event {
+        crossterm::InputEvent::Keyboard(k) if k != KeyEvent::Null => {
+            Some(InputEvent::Key(Key::from(k)))
+        }
+        // Crossterm counts the cells from 1.
+        crossterm::InputEvent::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) if mouse => Some(
+            InputEvent::Mouse((x.saturating_sub(1), y.saturating_sub(1))),
+        ),
+        _ => None,
+    }
+}
+
 /// A crossterm based input reader.
 pub struct CrosstermInputReader {
     input: TerminalInput,
+    /// The size of the terminal when it was last checked, to tell when it changes.
+    screen_size: Cell<ScreenSize>,
+    terminal: Terminal,
 }
 
 impl CrosstermInputReader {
     pub fn new() -> Self {
+        let terminal = Terminal::new();
         CrosstermInputReader {
             input: TerminalInput::new(),
+            screen_size: Cell::new(terminal.terminal_size()),
+            terminal,
         }
     }
-}
 
-impl InputReader for CrosstermInputReader {
-    fn read_key(&self) -> Key {
-        if let Ok(_) = RawScreen::into_raw_mode() {
-            loop {
+    /// Indicates whether the size of the terminal changed since the last check.
+    #[cfg(unix)]
+    fn has_screen_size_changed(&self) -> bool {
+        let size = self.terminal.terminal_size();
+        self.screen_size.replace(size) != size
+    }
+
+    /// Waits for a key, a left click if `mouse` is set, or a change of the terminal size, for at most the given duration if any. Returns `None` if nothing happened in time, or if there is no more input to read.
+    ///
+    /// Raw mode is entered once for the whole wait and left as soon as it is over, so that the terminal is back to normal for rendering and reading lines. The wait blocks until input is ready, only waking up now and then to check the size of the terminal.
+    #[cfg(unix)]
+    fn next_event(&self, mouse: bool, timeout: Option<Duration>) -> Option<InputEvent> {
+        let _raw_screen = RawScreen::into_raw_mode().ok()?;
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut reader = self.input.read_sync();
+        loop {
+            if self.has_screen_size_changed() {
+                return Some(InputEvent::Resize);
+            }
+            let mut wait = RESIZE_CHECK_INTERVAL;
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                wait = wait.min(deadline - now);
+            }
+            let mut stdin = libc::pollfd {
+                fd: libc::STDIN_FILENO,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut stdin, 1, wait.as_millis() as libc::c_int) } {
+                0 => continue,
+                n if n < 0 => {
+                    // The resize signal can interrupt the wait, which is then simply resumed.
+                    if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return None;
+                }
+                // The input was closed.
+                _ if stdin.revents & libc::POLLIN == 0 => return None,
+                _ => {}
+            }
+            // Only read once we know an event is waiting, so that the read does not block.
+            if let Some(event) = reader.next().and_then(|e| convert_event(e, mouse)) {
+                return Some(event);
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn next_event(&self, mouse: bool, timeout: Option<Duration>) -> Option<InputEvent> {
+        let _raw_screen = RawScreen::into_raw_mode().ok()?;
+        let deadline = match timeout {
+            Some(timeout) => Instant::now() + timeout,
+            None => {
                 let mut reader = self.input.read_sync();
-                if let Some(crossterm::InputEvent::Keyboard(k)) = reader.next() {
-                    if k != KeyEvent::Null {
-                        return Key::from(k);
+                loop {
+                    if let Some(event) = reader.next().and_then(|e| convert_event(e, mouse)) {
+                        return Some(event);
+                    }
+                }
+            }
+        };
+        // There is no way to wait for an event with a timeout here, so check the events read on another thread until the time is over.
+        let mut reader = self.input.read_async();
+        while Instant::now() < deadline {
+            match reader.next() {
+                Some(event) => {
+                    if let Some(event) = convert_event(event, mouse) {
+                        return Some(event);
                     }
                 }
+                None => std::thread::sleep(POLL_INTERVAL),
             }
         }
-        Key::Unknown
+        None
+    }
+
+    /// Waits for the next event as `next_event` does, capturing the mouse for the time of the wait if `mouse` is set.
+    fn next_event_with_mouse(&self, mouse: bool, timeout: Option<Duration>) -> Option<InputEvent> {
+        // Only capture the mouse while waiting, so that the terminal keeps its own mouse handling (such as text selection) the rest of the time.
+        if mouse {
+            let _ = self.input.enable_mouse_mode();
+        }
+        let event = self.next_event(mouse, timeout);
+        if mouse {
+            let _ = self.input.disable_mouse_mode();
+        }
+        event
+    }
+}
+
+impl InputReader for CrosstermInputReader {
+    fn poll_key(&self, mode: InputMode, timeout: Duration) -> Option<InputEvent> {
+        self.next_event_with_mouse(mode == InputMode::KeyAndMouse, Some(timeout))
+    }
+
+    fn read_key(&self) -> InputEvent {
+        self.next_event(false, None)
+            .unwrap_or(InputEvent::Key(Key::Unknown))
+    }
+
+    fn read_key_or_click(&self) -> InputEvent {
+        self.next_event_with_mouse(true, None)
+            .unwrap_or(InputEvent::Key(Key::Unknown))
     }
 
     fn read_line(&self) -> Result<String, std::io::Error> {
@@ -1,20 +1,324 @@
 mod app;
+mod error;
 mod game;
 mod input;
 mod menus;
+mod network;
+mod random;
 mod rendering;
+mod scores;
+mod settings;
 mod states;
 
 use app::App;
+use game::{
+    artificial_intelligence::{minimax, minimax_timed, Move},
+    board::{Board, GameOutcome, ParseError, Tile},
+    players::{OpponentKind, MAX_DIFFICULTY},
+};
 use input::CrosstermInputReader;
-use rendering::CrosstermRenderer;
-use states::main_menu_state::MainMenuState;
+use network::Connection;
+use rendering::{CrosstermRenderer, Renderer, WriteRenderer};
+use scores::{LifetimeScores, SharedScoreboard};
+use settings::Settings;
+use states::{
+    choose_opponent_menu_state::ChooseOpponentMenuState, main_menu_state::MainMenuState,
+    playing_state::PlayingState, splash_state::SplashState, State,
+};
+use std::{
+    cell::RefCell,
+    env,
+    fs::OpenOptions,
+    io::{self, Write},
+    process,
+    rc::Rc,
+    str::FromStr,
+    time::Duration,
+};
 
-fn main() {
-    App::new(
-        CrosstermRenderer::new(),
-        CrosstermInputReader::new(),
-        Box::new(MainMenuState::new()),
+/// Reads the value of an option given on the command line, either as `<option> <value>` or `<option>=<value>`.
+fn parse_option<A>(mut args: A, option: &str) -> Option<String>
+where
+    A: Iterator<Item = String>,
+{
+    let prefix = format!("{}=", option);
+    while let Some(arg) = args.next() {
+        let value = if arg == option {
+            args.next()
+        } else {
+            arg.strip_prefix(&prefix).map(|value| value.to_string())
+        };
+        if value.is_some() {
+            return value;
+        }
+    }
+    None
+}
+
+/// Reads the opponent given on the command line with `--opponent`. See `OpponentKind::from_name` for the accepted names.
+fn parse_opponent<A>(args: A) -> Option<OpponentKind>
+where
+    A: Iterator<Item = String>,
+{
+    parse_option(args, "--opponent").and_then(|name| OpponentKind::from_name(&name))
+}
+
+/// Reads a port number given on the command line, exiting if it is not valid.
+fn parse_port(port: &str) -> u16 {
+    match port.parse::<u16>() {
+        Ok(port) => port,
+        Err(_) => {
+            eprintln!("tic_tac_toe: the port must be a number from 0 to 65535");
+            process::exit(2);
+        }
+    }
+}
+
+/// Connects to the other player of a network game, if `--host <port>` or `--join <address>` is given on the command line. Returns the connection, and whether this side hosts the game.
+fn connect<A>(args: A) -> Option<(Connection, bool)>
+where
+    A: Iterator<Item = String> + Clone,
+{
+    let result = if let Some(port) = parse_option(args.clone(), "--host") {
+        let port = parse_port(&port);
+        println!("Waiting for the other player to join on port {}...", port);
+        Connection::host(port).map(|c| (c, true))
+    } else if let Some(address) = parse_option(args, "--join") {
+        Connection::join(&address).map(|c| (c, false))
+    } else {
+        return None;
+    };
+    match result {
+        Ok(connection) => Some(connection),
+        Err(e) => {
+            eprintln!("tic_tac_toe: could not connect to the other player: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Appends a finished game to the log at the given path, as one line: the tile of the winner or `draw`, followed by the spots played, in order, as `x,y`.
+fn log_game(path: &str, outcome: &GameOutcome) -> io::Result<()> {
+    let mut line = match outcome.winner {
+        Some(tile) => tile.to_save_char().to_string(),
+        None => String::from("draw"),
+    };
+    for (x, y) in &outcome.moves {
+        line.push_str(&format!(" {},{}", x, y));
+    }
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// The longest the search for the best move lasts on a board larger than 3x3, whose games can't all be explored in time.
+const SOLVE_BUDGET: Duration = Duration::from_secs(10);
+
+/// Finds the best move on a board drawn as text (see `Board::from_str`), for the player whose turn it is: the one with fewer tiles, or x if both have as many.
+fn solve(board: &str) -> Result<(Board, Tile, Move), ParseError> {
+    let mut board = Board::from_str(board)?;
+    let count = |tile| board.iter().filter(|(_, t)| **t == tile).count();
+    let player = if count(Tile::X) > count(Tile::O) {
+        Tile::O
+    } else {
+        Tile::X
+    };
+    let best_move = if board.size() > 3 {
+        minimax_timed(&mut board, player, SOLVE_BUDGET)
+    } else {
+        minimax(&mut board, player)
+    };
+    Ok((board, player, best_move))
+}
+
+/// Describes the result of `solve` as printed by the `solve` subcommand: the best move and its score, or that the game is already over.
+fn describe_solution(board: &Board, player: Tile, best_move: Move) -> String {
+    if board.status().is_finished() {
+        return String::from("The game is already over.\n");
+    }
+    format!(
+        "{} plays {} ({}, {})\nscore: {}\n",
+        player.to_save_char(),
+        board.get_position_label(best_move.pos),
+        best_move.pos.0,
+        best_move.pos.1,
+        best_move.score
     )
-    .run();
+}
+
+/// Runs the `solve` subcommand: prints the best move on the given board and its score, positive scores favoring x.
+fn run_solve(board: &str) {
+    match solve(board) {
+        Ok((board, player, best_move)) => {
+            print!("{}", describe_solution(&board, player, best_move))
+        }
+        Err(e) => {
+            let message = match e {
+                ParseError::Inconsistent => "this position can't be reached by playing a game",
+                ParseError::InvalidDimensions => "the board must be square",
+                ParseError::InvalidTile => "tiles must be 'x', 'o', '.' or ' '",
+                _ => "the board could not be read",
+            };
+            eprintln!("tic_tac_toe: {}", message);
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if args.first().map(|a| a.as_str()) == Some("solve") {
+        match args.get(1) {
+            Some(board) => run_solve(board),
+            None => {
+                eprintln!("usage: tic_tac_toe solve \"<board>\"");
+                process::exit(2);
+            }
+        }
+        return;
+    }
+    if let Some(seed) = parse_option(args.iter().cloned(), "--seed") {
+        match seed.parse::<u64>() {
+            Ok(seed) => random::seed(seed),
+            Err(_) => {
+                eprintln!("tic_tac_toe: the seed must be a non-negative integer");
+                process::exit(2);
+            }
+        }
+    }
+    let settings = Rc::new(RefCell::new(Settings::default()));
+    if let Some(difficulty) = parse_option(args.iter().cloned(), "--difficulty") {
+        match difficulty.parse::<u8>() {
+            Ok(difficulty) if difficulty <= MAX_DIFFICULTY => {
+                settings.borrow_mut().computer_difficulty = difficulty
+            }
+            _ => {
+                eprintln!(
+                    "tic_tac_toe: the difficulty must be a number from 0 to {}",
+                    MAX_DIFFICULTY
+                );
+                process::exit(2);
+            }
+        }
+    }
+    let opponent = parse_opponent(args.iter().cloned());
+    let connection = connect(args.iter().cloned());
+    // The splash screen is only shown when starting from the main menu.
+    let first_state: Box<dyn State> =
+        if opponent.is_some() || connection.is_some() || args.iter().any(|a| a == "--no-splash") {
+            Box::new(MainMenuState::new(settings.clone()))
+        } else {
+            Box::new(SplashState::new(settings.clone()))
+        };
+    // Follow the NO_COLOR convention (https://no-color.org), and leave the colors out of terminals known to lack them.
+    let has_colors = !args.iter().any(|a| a == "--no-color")
+        && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        && env::var("TERM").map_or(true, |t| t != "dumb");
+    let renderer = if has_colors {
+        CrosstermRenderer::new()
+    } else {
+        CrosstermRenderer::without_colors()
+    };
+    let screen_size = renderer.get_screen_size();
+    // Stream the game to a spectator, who follows it with a terminal of the same size.
+    let mirror = parse_option(args.iter().cloned(), "--relay").map(|port| {
+        let port = parse_port(&port);
+        println!("Waiting for a spectator to connect on port {}...", port);
+        match network::accept_spectator(port) {
+            Ok(stream) => WriteRenderer::with_ansi(stream, screen_size),
+            Err(e) => {
+                eprintln!("tic_tac_toe: could not connect to the spectator: {}", e);
+                process::exit(1);
+            }
+        }
+    });
+    let mut app = App::new(renderer, CrosstermInputReader::new(), first_state);
+    if let Some(mirror) = mirror {
+        app.set_mirror(Box::new(mirror));
+    }
+    if args.iter().any(|a| a == "--log-transitions") {
+        app.enable_transition_log();
+    }
+    let log_path = parse_option(args.iter().cloned(), "--log");
+    app.set_game_outcome_callback(move |outcome| {
+        if let Some(path) = &log_path {
+            // Like the scores, a log that can't be written should not stop the game.
+            let _ = log_game(path, &outcome);
+        }
+        if let Some(tile) = outcome.winner {
+            let mut lifetime_scores = LifetimeScores::load();
+            lifetime_scores.record_win(tile);
+            // Failing to persist the scores should not prevent the game from going on.
+            let _ = lifetime_scores.save();
+        }
+    });
+    // Start a game right away if an opponent was given (or a network game), as if it had been chosen from the menus: leaving the game goes back to the choice of the opponent.
+    let scoreboard = SharedScoreboard::default();
+    let game = match connection {
+        Some((connection, is_host)) => Some(PlayingState::with_remote_player(
+            Rc::new(connection),
+            is_host,
+            scoreboard.clone(),
+            settings.clone(),
+        )),
+        None => opponent.map(|opponent| {
+            PlayingState::with_opponent(opponent, Tile::X, scoreboard.clone(), settings.clone())
+        }),
+    };
+    if let Some(game) = game {
+        app.push_state(Box::new(ChooseOpponentMenuState::new(scoreboard, settings)));
+        app.push_state(Box::new(game));
+    }
+    let result = app.run();
+    if let Some(log) = app.get_transition_log() {
+        let log = log.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        eprintln!(
+            "transitions: {} ({} states left)",
+            log.join(" "),
+            app.get_depth()
+        );
+    }
+    if let Err(e) = result {
+        eprintln!("tic_tac_toe: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Solves the given board and describes the result.
+    fn solve_to_text(board: &str) -> String {
+        let (board, player, best_move) = solve(board).ok().unwrap();
+        describe_solution(&board, player, best_move)
+    }
+
+    #[test]
+    fn solve_completes_the_line_of_the_player_to_move() {
+        // Both players have two tiles, so x moves, and wins right away on the top row.
+        let (_, player, best_move) = solve("xx.\noo.\n...").ok().unwrap();
+        assert!(player == Tile::X);
+        assert_eq!(best_move.pos, (2, 0));
+        assert_eq!(
+            solve_to_text("xx.\noo.\n..."),
+            "x plays c3 (2, 0)\nscore: 999\n"
+        );
+        // o has fewer tiles, and must block the top row, which only delays its loss: x then threatens two lines at once.
+        assert_eq!(
+            solve_to_text("xx.\no..\n..."),
+            "o plays c3 (2, 0)\nscore: 996\n"
+        );
+    }
+
+    #[test]
+    fn solve_tells_when_the_game_is_over() {
+        assert_eq!(
+            solve_to_text("xxx\noo.\n..."),
+            "The game is already over.\n"
+        );
+        assert!(matches!(
+            solve("xx\n..."),
+            Err(ParseError::InvalidDimensions)
+        ));
+    }
 }
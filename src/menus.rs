@@ -1,101 +1,404 @@
 use crate::{
-    input::{InputEvent, InputMode},
-    rendering::{Color, Error, Renderer},
+    game::board::GameOutcome,
+    input::{InputEvent, InputMode, Key},
+    rendering::{render_centered, BufferRenderer, Color, Error, Renderer},
     states::{State, StateTransition},
 };
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    time::Duration,
+};
 use textwrap::wrap;
 
+/// The lines that show the next page of a long menu. Pressing PageDown before enter types the last one.
+const NEXT_PAGE_INPUTS: [&str; 2] = [">", "\u{1b}[6~"];
+
+/// The lines that show the previous page of a long menu. Pressing PageUp before enter types the last one.
+const PREVIOUS_PAGE_INPUTS: [&str; 2] = ["<", "\u{1b}[5~"];
+
 /// Represents an item in a menu.
 pub struct MenuEntry {
-    /// The color that will be used to draw this entry.
-    pub color: Color,
+    /// The color used to draw this entry, instead of the text color of the theme.
+    pub color: Option<Color>,
+    /// An explanation drawn dimmed under the text, if any.
+    pub help: Option<String>,
     /// Indicates whether this entry can be triggered. If you don't want to present it to the user, consider setting `is_visible = false` instead.
     pub is_enabled: bool,
+    /// Indicates whether the key of this entry is drawn in bold.
+    pub is_key_bold: bool,
     /// Indicates whether this entry will be drawn.
     pub is_visible: bool,
-    /// The number that will trigger this entry.
-    pub key: usize,
+    /// The character that will trigger this entry, a digit or a letter (matched whatever its case).
+    pub key: char,
     /// The text to display.
     pub text: String,
 }
 
 impl MenuEntry {
     /// Constructs a new menu entry with the given text and key.
-    pub fn new<S>(text: S, key: usize) -> Self
+    pub fn new<S>(text: S, key: char) -> Self
     where
         S: Into<String>,
     {
         MenuEntry {
-            color: Color::White,
+            color: None,
+            help: None,
             is_enabled: true,
+            is_key_bold: true,
             is_visible: true,
             key,
             text: text.into(),
         }
     }
 
-    /// Renders this entry to the terminal.
-    fn render(&self, renderer: &Renderer) -> Result<(), Error> {
+    /// Gets the number of lines this entry takes once rendered on a terminal of the given width, with the given theme if the selection is shown.
+    fn get_height(&self, screen_width: u16, theme: Option<&MenuTheme>) -> usize {
+        self.wrap_text(screen_width, theme).len() + self.wrap_help(screen_width, theme).len()
+    }
+
+    /// Gets the width of the selection marker and the key drawn before the text, which is also the indentation of the following lines.
+    fn get_margin(&self, theme: Option<&MenuTheme>) -> usize {
+        // The key (or the mark of a disabled entry) is followed by ") ".
+        theme.map_or(0, |t| t.selection_marker.chars().count()) + 3
+    }
+
+    /// Indicates whether the given character triggers this entry.
+    fn is_triggered_by(&self, c: char) -> bool {
+        self.key.to_lowercase().eq(c.to_lowercase())
+    }
+
+    /// Indicates whether this entry can be chosen by the user.
+    fn is_selectable(&self) -> bool {
+        self.is_enabled && self.is_visible
+    }
+
+    /// Renders this entry to the terminal, with the colors of the given theme. If the selection is shown, the selected entry is drawn after the selection marker and with the selection colors, the others are indented to stay aligned.
+    fn render(
+        &self,
+        renderer: &dyn Renderer,
+        theme: &MenuTheme,
+        show_selection: bool,
+        is_selected: bool,
+    ) -> Result<(), Error> {
         if self.is_visible {
-            let margin = if self.is_enabled {
-                let key_string = self.key.to_string();
-                renderer.set_foreground_color(self.color.clone())?;
-                renderer.write(&key_string)?;
+            let color = self.color.clone().unwrap_or_else(|| theme.text.clone());
+            let selection = if show_selection { Some(theme) } else { None };
+            if show_selection {
+                if is_selected {
+                    renderer.write(&theme.selection_marker)?;
+                } else {
+                    renderer.write(&" ".repeat(theme.selection_marker.chars().count()))?;
+                }
+            }
+            if self.is_enabled {
+                renderer.set_foreground_color(color.clone())?;
+                if self.is_key_bold {
+                    renderer.set_bold(true)?;
+                    renderer.write(&self.key.to_string())?;
+                    renderer.set_bold(false)?;
+                } else {
+                    renderer.write(&self.key.to_string())?;
+                }
                 renderer.write(") ")?;
-                key_string.len() + 2
             } else {
-                renderer.set_foreground_color(Color::Red)?;
+                renderer.set_foreground_color(theme.disabled.clone())?;
                 renderer.write("x) ")?;
-                renderer.set_foreground_color(self.color.clone())?;
-                4
-            };
-            let indent_string = (0..margin).map(|_| " ").collect::<String>();
-            for (index, line) in wrap(&self.text, (renderer.get_screen_size().0 as usize) - margin)
+                renderer.set_foreground_color(color.clone())?;
+            }
+            let indent_string = (0..self.get_margin(selection))
+                .map(|_| " ")
+                .collect::<String>();
+            for (index, line) in self
+                .wrap_text(renderer.get_screen_size().0, selection)
                 .iter()
                 .enumerate()
             {
                 if index > 0 {
                     renderer.write(&indent_string)?;
                 }
+                if show_selection && is_selected {
+                    renderer.set_background_color(theme.selection_background.clone())?;
+                    renderer.set_foreground_color(theme.selection_foreground.clone())?;
+                    let result = renderer.write(line);
+                    // Reset the colors even if the write failed, so that the rest of the screen is not painted.
+                    renderer.set_background_color(Color::Reset)?;
+                    renderer.set_foreground_color(color.clone())?;
+                    result?;
+                } else {
+                    renderer.write(line)?;
+                }
+                renderer.write("\n")?;
+            }
+            renderer.set_foreground_color(theme.dimmed.clone())?;
+            for line in self
+                .wrap_help(renderer.get_screen_size().0, selection)
+                .iter()
+            {
+                renderer.write(&indent_string)?;
                 renderer.write(line)?;
                 renderer.write("\n")?;
             }
+            renderer.set_foreground_color(color)?;
         }
         Ok(())
     }
+
+    /// Splits the help into the lines drawn under the text, indented as the text. Empty if there is no help.
+    fn wrap_help(&self, screen_width: u16, theme: Option<&MenuTheme>) -> Vec<Cow<'_, str>> {
+        match self.help {
+            Some(ref help) => {
+                let width = (screen_width as usize)
+                    .saturating_sub(self.get_margin(theme))
+                    .max(1);
+                wrap(help, width)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Splits the text into the lines drawn on a terminal of the given width. The width is read on every render, so that the text follows the size of the terminal, however small it gets.
+    fn wrap_text(&self, screen_width: u16, theme: Option<&MenuTheme>) -> Vec<Cow<'_, str>> {
+        let width = (screen_width as usize)
+            .saturating_sub(self.get_margin(theme))
+            .max(1);
+        wrap(&self.text, width)
+    }
+}
+
+/// The colors of a menu, and how its selected entry is drawn when entries are chosen with the arrow keys.
+#[derive(Clone, PartialEq)]
+pub struct MenuTheme {
+    /// The color of the help of the entries, and of the indicators of more entries.
+    pub dimmed: Color,
+    /// The color of the mark of a disabled entry.
+    pub disabled: Color,
+    /// The background color of the text of the selected entry.
+    pub selection_background: Color,
+    /// The color of the text of the selected entry.
+    pub selection_foreground: Color,
+    /// Drawn before the selected entry. The other entries are indented by as many spaces.
+    pub selection_marker: String,
+    /// The color of the entries.
+    pub text: Color,
+}
+
+impl MenuTheme {
+    /// Constructs a theme for low-vision users: bright white text, and the selected entry drawn black on white.
+    pub fn high_contrast() -> Self {
+        MenuTheme {
+            dimmed: Color::White,
+            disabled: Color::Yellow,
+            selection_background: Color::White,
+            selection_foreground: Color::Black,
+            selection_marker: "> ".to_string(),
+            text: Color::White,
+        }
+    }
+}
+
+impl Default for MenuTheme {
+    /// White entries, a `>` before the selected one, over a dark blue background.
+    fn default() -> Self {
+        MenuTheme {
+            dimmed: Color::DarkGrey,
+            disabled: Color::Red,
+            selection_background: Color::DarkBlue,
+            selection_foreground: Color::White,
+            selection_marker: "> ".to_string(),
+            text: Color::White,
+        }
+    }
 }
 
 /// Represents a menu entry identifier that is guarantied to be unique across a menu.
 #[derive(Clone, Copy, PartialEq)]
 pub struct MenuEntryId(usize);
 
+/// Computes how many entries fit on a page starting at the entry `first`, given the height of every entry in lines and the number of lines available. A line is kept for each indicator of more entries above or below the page. At least one entry is always shown, even if it does not fit.
+fn page_length(heights: &[usize], first: usize, lines: usize) -> usize {
+    if first >= heights.len() {
+        return 0;
+    }
+    let fit = |lines: usize| {
+        let mut used = 0;
+        heights[first..]
+            .iter()
+            .take_while(|h| {
+                used += **h;
+                used <= lines
+            })
+            .count()
+    };
+    let lines = if first > 0 {
+        lines.saturating_sub(1)
+    } else {
+        lines
+    };
+    let mut length = fit(lines);
+    if first + length < heights.len() {
+        length = fit(lines.saturating_sub(1));
+    }
+    length.max(1)
+}
+
 /// Represents a menu.
+///
+/// A menu too long for the terminal is split into pages, the user moving between them by typing `>` and `<` (or with the PageDown and PageUp keys).
+///
+/// Entries are chosen either by typing their number, or by moving a selection with the arrow keys (see `handle_key`).
 pub struct Menu {
+    default_entry: Option<MenuEntryId>,
     entries: Vec<(MenuEntryId, MenuEntry)>,
+    /// The index, among the visible entries, of the first one on the current page.
+    first_shown: Cell<usize>,
     next_id: usize,
+    /// The number of entries on the current page, known once the menu was rendered.
+    page_length: Cell<usize>,
+    /// The first entry of each of the pages before the current one, to go back to them.
+    previous_pages: RefCell<Vec<usize>>,
+    /// The entry chosen when pressing enter, when entries are chosen with the arrow keys.
+    selected: Cell<Option<MenuEntryId>>,
 }
 
 impl Menu {
     /// Constructs a new menu.
     pub fn new() -> Self {
         Menu {
+            default_entry: None,
             entries: Vec::new(),
+            first_shown: Cell::new(0),
             next_id: 0,
+            page_length: Cell::new(0),
+            previous_pages: RefCell::new(Vec::new()),
+            selected: Cell::new(None),
         }
     }
 
-    /// Handles input for this menu. It will return an identifier to the choosen entry, or `None` if it failed to parse user input.
-    pub fn handle_input(&self, input: &str) -> Option<MenuEntryId> {
-        if let Ok(key) = input.parse::<usize>() {
-            if let Some(entry) = self.entries.iter().filter(|m| m.1.key == key).next() {
-                if entry.1.is_enabled && entry.1.is_visible {
-                    return Some(entry.0);
-                }
+    /// Gets a mutable reference to an entry, to update it after it was pushed.
+    pub fn get_entry_mut(&mut self, entry: MenuEntryId) -> Option<&mut MenuEntry> {
+        self.entries
+            .iter_mut()
+            .find(|e| e.0 == entry)
+            .map(|e| &mut e.1)
+    }
+
+    /// Gets the entry chosen when pressing enter: the last one selected if it can still be chosen, or else the default entry, or else the first one that can be chosen.
+    pub fn get_selected(&self) -> Option<MenuEntryId> {
+        let is_selectable = |id: MenuEntryId| {
+            self.entries
+                .iter()
+                .any(|e| e.0 == id && e.1.is_selectable())
+        };
+        self.selected
+            .get()
+            .filter(|id| is_selectable(*id))
+            .or_else(|| self.default_entry.filter(|id| is_selectable(*id)))
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|e| e.1.is_selectable())
+                    .map(|e| e.0)
+            })
+    }
+
+    /// Finds the entry triggered by the given character, if it can be chosen.
+    fn find_by_key(&self, c: char) -> Option<MenuEntryId> {
+        self.entries
+            .iter()
+            .find(|e| e.1.is_triggered_by(c) && e.1.is_selectable())
+            .map(|e| e.0)
+    }
+
+    /// Handles a key press, when entries are chosen with the arrow keys. Up and down move the selection (going around at both ends), enter chooses the selected entry and the key of an entry chooses it right away. Returns the chosen entry, if any.
+    pub fn handle_key(&self, key: &Key) -> Option<MenuEntryId> {
+        match key {
+            Key::Char('\n') => return self.get_selected(),
+            Key::Char(c) => return self.find_by_key(*c),
+            Key::Down => self.move_selection(1),
+            Key::PageDown => {
+                self.next_page();
+                self.select_first_shown();
             }
+            Key::PageUp => {
+                self.previous_page();
+                self.select_first_shown();
+            }
+            Key::Up => self.move_selection(-1),
+            _ => {}
         }
         None
     }
 
+    /// Handles input for this menu. It will return an identifier to the choosen entry, or `None` if it failed to parse user input. The entry is found from the first typed character, and an empty input chooses the default entry, if any.
+    pub fn handle_input(&self, input: &str) -> Option<MenuEntryId> {
+        if input.trim().is_empty() {
+            return self.default_entry;
+        }
+        if NEXT_PAGE_INPUTS.contains(&input.trim()) {
+            self.next_page();
+            return None;
+        }
+        if PREVIOUS_PAGE_INPUTS.contains(&input.trim()) {
+            self.previous_page();
+            return None;
+        }
+        input
+            .trim()
+            .chars()
+            .next()
+            .and_then(|c| self.find_by_key(c))
+    }
+
+    /// Moves the selection by the given number of entries, skipping the ones that can't be chosen and going around at both ends.
+    fn move_selection(&self, offset: isize) {
+        let selectable = self
+            .entries
+            .iter()
+            .filter(|e| e.1.is_selectable())
+            .map(|e| e.0)
+            .collect::<Vec<MenuEntryId>>();
+        if selectable.is_empty() {
+            return;
+        }
+        let current = self
+            .get_selected()
+            .and_then(|id| selectable.iter().position(|s| *s == id))
+            .unwrap_or(0) as isize;
+        let next = (current + offset).rem_euclid(selectable.len() as isize);
+        self.selected.set(Some(selectable[next as usize]));
+    }
+
+    /// Shows the next page of entries, if any.
+    pub fn next_page(&self) {
+        let first = self.first_shown.get();
+        let next = first + self.page_length.get();
+        if next < self.entries.iter().filter(|e| e.1.is_visible).count() {
+            self.previous_pages.borrow_mut().push(first);
+            self.first_shown.set(next);
+        }
+    }
+
+    /// Shows the previous page of entries, if any.
+    pub fn previous_page(&self) {
+        if let Some(first) = self.previous_pages.borrow_mut().pop() {
+            self.first_shown.set(first);
+        }
+    }
+
+    /// Selects the first entry of the current page that can be chosen, if any.
+    fn select_first_shown(&self) {
+        if let Some(entry) = self
+            .entries
+            .iter()
+            .filter(|e| e.1.is_visible)
+            .skip(self.first_shown.get())
+            .find(|e| e.1.is_enabled)
+        {
+            self.selected.set(Some(entry.0));
+        }
+    }
+
     /// Pushes a new entry to this menu. Returns the newly created entry identifier.
     pub fn push(&mut self, entry: MenuEntry) -> MenuEntryId {
         let id = MenuEntryId(self.next_id);
@@ -104,10 +407,96 @@ impl Menu {
         id
     }
 
-    /// Renders this menu to the terminal.
-    fn render(&self, renderer: &Renderer) -> Result<(), Error> {
-        for e in self.entries.iter() {
-            e.1.render(renderer)?;
+    /// Removes an entry from this menu. The identifiers of the other entries stay valid.
+    pub fn remove(&mut self, entry: MenuEntryId) {
+        self.entries.retain(|e| e.0 != entry);
+        if self.default_entry == Some(entry) {
+            self.default_entry = None;
+        }
+    }
+
+    /// Sets the entry chosen when the user validates an empty input.
+    pub fn set_default(&mut self, entry: MenuEntryId) {
+        self.default_entry = Some(entry);
+    }
+
+    /// Allows or forbids choosing an entry. Does nothing if the entry was removed.
+    pub fn set_enabled(&mut self, entry: MenuEntryId, is_enabled: bool) {
+        if let Some(entry) = self.get_entry_mut(entry) {
+            entry.is_enabled = is_enabled;
+        }
+    }
+
+    /// Shows or hides an entry. Does nothing if the entry was removed.
+    pub fn set_visible(&mut self, entry: MenuEntryId, is_visible: bool) {
+        if let Some(entry) = self.get_entry_mut(entry) {
+            entry.is_visible = is_visible;
+        }
+    }
+
+    /// Renders the current page of this menu to the terminal with the given theme, in at most the given number of lines. If the selection is shown, the selected entry is highlighted, and the page is moved so that it is shown.
+    fn render(
+        &self,
+        renderer: &dyn Renderer,
+        lines: usize,
+        theme: &MenuTheme,
+        show_selection: bool,
+    ) -> Result<(), Error> {
+        let selection = if show_selection { Some(theme) } else { None };
+        let screen_width = renderer.get_screen_size().0;
+        let visible_entries = self
+            .entries
+            .iter()
+            .filter(|e| e.1.is_visible)
+            .collect::<Vec<&(MenuEntryId, MenuEntry)>>();
+        // The terminal may have grown since the last page was computed, keeping the first entry on screen is enough.
+        let mut first = self
+            .first_shown
+            .get()
+            .min(visible_entries.len().saturating_sub(1));
+        let heights = visible_entries
+            .iter()
+            .map(|e| e.1.get_height(screen_width, selection))
+            .collect::<Vec<usize>>();
+        let selected = if show_selection {
+            self.get_selected()
+        } else {
+            None
+        };
+        let mut length = page_length(&heights, first, lines);
+        if let Some(index) = selected.and_then(|id| visible_entries.iter().position(|e| e.0 == id))
+        {
+            while index < first || index >= first + length {
+                if index < first {
+                    first = self.previous_pages.borrow_mut().pop().unwrap_or(0);
+                } else {
+                    self.previous_pages.borrow_mut().push(first);
+                    first += length;
+                }
+                length = page_length(&heights, first, lines);
+            }
+        }
+        self.first_shown.set(first);
+        self.page_length.set(length);
+        if first > 0 {
+            renderer.set_foreground_color(theme.dimmed.clone())?;
+            renderer.write(if show_selection {
+                "(more above)\n"
+            } else {
+                "(more above, type < to see them)\n"
+            })?;
+        }
+        for e in visible_entries[first..first + length].iter() {
+            e.1.render(renderer, theme, show_selection, selected == Some(e.0))?;
+        }
+        if first + length < visible_entries.len() {
+            renderer.set_foreground_color(theme.dimmed.clone())?;
+            renderer.write(if show_selection {
+                "(more below)\n"
+            } else {
+                "(more below, type > to see them)\n"
+            })?;
+            renderer.set_foreground_color(Color::Reset)?;
         }
         Ok(())
     }
@@ -115,36 +504,251 @@ impl Menu {
 
 /// A convenient trait to create states that only deal with a menu.
 pub trait MenuState: State {
+    /// Gets the time to wait before the next frame of an animation of the header, if any.
+    fn get_frame_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Gets the outcome of the game this menu reports, if any.
+    fn get_game_outcome(&self) -> Option<GameOutcome> {
+        None
+    }
+
+    /// Gets the way the user chooses an entry: by typing its number (`InputMode::Line`, the default), or with the arrow keys (`InputMode::Key`).
+    fn get_input_mode(&self) -> InputMode {
+        InputMode::Line
+    }
+
+    /// Gets the colors of the menu, and how the selected entry is drawn when entries are chosen with the arrow keys.
+    fn get_menu_theme(&self) -> MenuTheme {
+        MenuTheme::default()
+    }
+
     /// Should return a reference to the menu.
     fn get_menu(&self) -> &Menu;
 
     /// Used to handle user's choice.
     fn handle_selection(&mut self, entry: MenuEntryId) -> StateTransition;
 
+    /// Indicates whether this menu is shown once the game is over.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+
     /// Used to render something on top of the screen.
-    fn render_header(&self, renderer: &Renderer) -> Result<(), Error>;
+    fn render_header(&self, renderer: &dyn Renderer) -> Result<(), Error>;
+
+    /// Called when this menu is current again, after the state on top of it was popped.
+    fn resume(&mut self) {}
+
+    /// Jumps to the last frame of the animation of the header.
+    fn skip_animation(&mut self) {}
+
+    /// Indicates whether the bell should ring, forgetting the request so that it rings only once.
+    fn take_bell(&self) -> bool {
+        false
+    }
+
+    /// Advances the animation of the header by one frame. The last frame can lead to another state.
+    fn tick(&mut self) -> StateTransition {
+        StateTransition::None
+    }
 }
 
 impl<T> State for T
 where
     T: MenuState,
 {
+    fn get_frame_delay(&self) -> Option<Duration> {
+        MenuState::get_frame_delay(self)
+    }
+
+    fn get_game_outcome(&self) -> Option<GameOutcome> {
+        MenuState::get_game_outcome(self)
+    }
+
     fn get_input_mode(&self) -> InputMode {
-        InputMode::Line
+        MenuState::get_input_mode(self)
     }
 
     fn handle_input(&mut self, input: InputEvent) -> StateTransition {
-        if let InputEvent::Line(ref input) = input {
-            if let Some(entry) = self.get_menu().handle_input(input) {
-                return self.handle_selection(entry);
-            }
+        let entry = match input {
+            InputEvent::Key(ref key) => self.get_menu().handle_key(key),
+            InputEvent::Line(ref input) => self.get_menu().handle_input(input),
+            _ => None,
+        };
+        match entry {
+            Some(entry) => self.handle_selection(entry),
+            None => StateTransition::None,
         }
-        StateTransition::None
     }
 
-    fn render(&self, renderer: &Renderer) -> Result<(), Error> {
-        renderer.clear()?;
-        self.render_header(renderer)?;
-        self.get_menu().render(renderer)
+    fn is_terminal(&self) -> bool {
+        MenuState::is_terminal(self)
+    }
+
+    fn render(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        // Draw the header off screen first, to measure how many lines are left for the menu. The last line is kept for the user to type in.
+        let header = BufferRenderer::like(renderer);
+        self.render_header(&header)?;
+        let (x, y) = header.get_cursor_position();
+        let header_height = y as usize + if x > 0 { 1 } else { 0 };
+        let lines = (renderer.get_screen_size().1 as usize).saturating_sub(header_height + 1);
+        let theme = self.get_menu_theme();
+        let show_selection = match MenuState::get_input_mode(self) {
+            InputMode::Key | InputMode::KeyAndMouse => true,
+            InputMode::Line => false,
+        };
+        render_centered(renderer, |renderer| {
+            renderer.clear()?;
+            self.render_header(renderer)?;
+            self.get_menu()
+                .render(renderer, lines, &theme, show_selection)
+        })
+        .map(|_| ())
+    }
+
+    fn resume(&mut self) {
+        MenuState::resume(self)
+    }
+
+    fn skip_animation(&mut self) {
+        MenuState::skip_animation(self)
+    }
+
+    fn take_bell(&self) -> bool {
+        MenuState::take_bell(self)
+    }
+
+    fn tick(&mut self) -> StateTransition {
+        MenuState::tick(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a menu with the entries "a", "b" and "c", returning their identifiers.
+    fn abc_menu() -> (Menu, [MenuEntryId; 3]) {
+        let mut menu = Menu::new();
+        let a = menu.push(MenuEntry::new("A", 'a'));
+        let b = menu.push(MenuEntry::new("B", 'b'));
+        let c = menu.push(MenuEntry::new("C", 'c'));
+        (menu, [a, b, c])
+    }
+
+    #[test]
+    fn disabled_entries_cannot_be_chosen() {
+        let (mut menu, [a, b, _]) = abc_menu();
+        menu.set_enabled(b, false);
+        assert!(menu.handle_input("b").is_none());
+        assert!(menu.handle_input("a") == Some(a));
+        menu.set_enabled(b, true);
+        assert!(menu.handle_input("b") == Some(b));
+    }
+
+    #[test]
+    fn selection_skips_disabled_and_hidden_entries() {
+        let (mut menu, [a, b, c]) = abc_menu();
+        menu.set_enabled(b, false);
+        assert!(menu.get_selected() == Some(a));
+        menu.handle_key(&Key::Down);
+        assert!(menu.get_selected() == Some(c));
+        menu.set_enabled(b, true);
+        menu.set_visible(b, false);
+        menu.handle_key(&Key::Down);
+        assert!(menu.get_selected() == Some(a));
+    }
+
+    #[test]
+    fn removing_an_entry_keeps_the_other_identifiers() {
+        let (mut menu, [a, b, c]) = abc_menu();
+        menu.set_default(b);
+        menu.remove(b);
+        assert!(menu.handle_input("b").is_none());
+        assert!(menu.handle_input("").is_none());
+        assert!(menu.handle_input("a") == Some(a));
+        assert!(menu.handle_input("c") == Some(c));
+        assert!(menu.get_entry_mut(b).is_none());
+        let d = menu.push(MenuEntry::new("D", 'd'));
+        assert!(d != a && d != b && d != c);
+        menu.set_enabled(b, false);
+        assert!(menu.handle_input("d") == Some(d));
+    }
+
+    /// Builds a menu with ten entries, "Entry 0" to "Entry 9", chosen by their digit.
+    fn long_menu() -> Menu {
+        let mut menu = Menu::new();
+        for i in 0..10 {
+            menu.push(MenuEntry::new(
+                format!("Entry {}", i),
+                std::char::from_digit(i, 10).unwrap(),
+            ));
+        }
+        menu
+    }
+
+    #[test]
+    fn long_menus_are_shown_page_by_page() {
+        let menu = long_menu();
+        let renderer = BufferRenderer::new((80, 40));
+        let theme = MenuTheme::default();
+        menu.render(&renderer, 5, &theme, false).ok().unwrap();
+        assert!(renderer.contents().contains("Entry 0"));
+        assert!(renderer.contents().contains("(more below"));
+        assert!(!renderer.contents().contains("Entry 9"));
+        assert!(menu.handle_input(">").is_none());
+        let _ = renderer.clear();
+        menu.render(&renderer, 5, &theme, false).ok().unwrap();
+        assert!(renderer.contents().contains("(more above"));
+        assert!(!renderer.contents().contains("Entry 0"));
+        assert!(menu.handle_input("<").is_none());
+        let _ = renderer.clear();
+        menu.render(&renderer, 5, &theme, false).ok().unwrap();
+        assert!(renderer.contents().contains("Entry 0"));
+        // Entries can be chosen whatever the page shown.
+        assert!(menu.handle_input("9").is_some());
+    }
+
+    #[test]
+    fn the_page_follows_the_selection() {
+        let menu = long_menu();
+        let renderer = BufferRenderer::new((80, 40));
+        let theme = MenuTheme::default();
+        menu.render(&renderer, 5, &theme, true).ok().unwrap();
+        assert!(!renderer.contents().contains("Entry 9"));
+        // Going up from the first entry selects the last one, which brings its page on screen.
+        menu.handle_key(&Key::Up);
+        let _ = renderer.clear();
+        menu.render(&renderer, 5, &theme, true).ok().unwrap();
+        assert!(renderer.contents().contains("Entry 9"));
+        assert!(!renderer.contents().contains("Entry 0"));
+    }
+
+    #[test]
+    fn arrow_keys_move_the_selection_and_enter_chooses_it() {
+        let (menu, [a, b, c]) = abc_menu();
+        assert!(menu.handle_key(&Key::Down).is_none());
+        assert!(menu.handle_key(&Key::Char('\n')) == Some(b));
+        menu.handle_key(&Key::Down);
+        assert!(menu.get_selected() == Some(c));
+        // The selection goes around at both ends.
+        menu.handle_key(&Key::Down);
+        assert!(menu.get_selected() == Some(a));
+        menu.handle_key(&Key::Up);
+        assert!(menu.handle_key(&Key::Char('\n')) == Some(c));
+    }
+
+    #[test]
+    fn letter_keys_choose_their_entry_whatever_the_case() {
+        let mut menu = Menu::new();
+        let play = menu.push(MenuEntry::new("Play", 'p'));
+        let quit = menu.push(MenuEntry::new("Quit", 'q'));
+        assert!(menu.handle_input("q") == Some(quit));
+        assert!(menu.handle_input("Q") == Some(quit));
+        assert!(menu.handle_input("play") == Some(play));
+        assert!(menu.handle_key(&Key::Char('P')) == Some(play));
+        assert!(menu.handle_input("x").is_none());
     }
 }
@@ -0,0 +1,168 @@
+use crate::game::board::PlayingPosition;
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+/// A connection to the computer of the other player of a network game.
+///
+/// The protocol is line based: each move is sent as its coordinates (see `encode_move`). Nothing else is shared, so both sides must agree on everything else, such as the player starting each game, without random choices.
+pub struct Connection {
+    /// Indicates whether the other player left, or sent something that is not a move. Nothing more is received then.
+    has_left: Cell<bool>,
+    /// The lines sent by the other player, read on another thread so that waiting for them does not block the game.
+    lines: Receiver<String>,
+    writer: RefCell<TcpStream>,
+}
+
+impl Connection {
+    /// Waits for another player to join on the given port.
+    pub fn host(port: u16) -> io::Result<Connection> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let (stream, _) = listener.accept()?;
+        Connection::new(stream)
+    }
+
+    /// Joins the game hosted at the given address, such as `localhost:7777`.
+    pub fn join(address: &str) -> io::Result<Connection> {
+        Connection::new(TcpStream::connect(address)?)
+    }
+
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            // Stops once the connection is closed, on either side.
+            for line in reader.lines() {
+                let is_sent = match line {
+                    Ok(line) => sender.send(line).is_ok(),
+                    Err(_) => false,
+                };
+                if !is_sent {
+                    break;
+                }
+            }
+        });
+        Ok(Connection {
+            has_left: Cell::new(false),
+            lines,
+            writer: RefCell::new(stream),
+        })
+    }
+
+    /// Indicates whether the other player left the game.
+    pub fn has_left(&self) -> bool {
+        self.has_left.get()
+    }
+
+    /// Gets the next move of the other player, or `None` if it did not arrive yet.
+    pub fn receive_move(&self) -> Option<PlayingPosition> {
+        if self.has_left.get() {
+            return None;
+        }
+        match self.lines.try_recv() {
+            Ok(line) => {
+                let position = decode_move(&line);
+                self.has_left.set(position.is_none());
+                position
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.has_left.set(true);
+                None
+            }
+        }
+    }
+
+    /// Sends a move to the other player.
+    pub fn send_move(&self, position: PlayingPosition) -> io::Result<()> {
+        let mut writer = self.writer.borrow_mut();
+        writeln!(writer, "{}", encode_move(position))?;
+        writer.flush()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // Also stops the thread reading the lines, and lets the other player know that we left.
+        let _ = self.writer.borrow().shutdown(Shutdown::Both);
+    }
+}
+
+/// Waits for a spectator to connect on the given port, to stream the game to it.
+pub fn accept_spectator(port: u16) -> io::Result<TcpStream> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.accept().map(|(stream, _)| stream)
+}
+
+/// Reads a move written by `encode_move`.
+pub fn decode_move(s: &str) -> Option<PlayingPosition> {
+    let mut coordinates = s.trim().split(',');
+    let x = coordinates.next()?.parse().ok()?;
+    let y = coordinates.next()?.parse().ok()?;
+    if coordinates.next().is_some() {
+        return None;
+    }
+    Some((x, y))
+}
+
+/// Writes a move the way it is sent over the network: its coordinates separated by a comma, such as `2,0` for the top right spot of a 3x3 board.
+pub fn encode_move(position: PlayingPosition) -> String {
+    format!("{},{}", position.0, position.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_round_trip() {
+        for &position in [(0, 0), (2, 0), (1, 2), (14, 9)].iter() {
+            assert_eq!(
+                encode_move(position),
+                format!("{},{}", position.0, position.1)
+            );
+            assert_eq!(decode_move(&encode_move(position)), Some(position));
+        }
+        assert_eq!(decode_move("2,1\r\n"), Some((2, 1)));
+    }
+
+    #[test]
+    fn malformed_moves_are_refused() {
+        for line in ["", "1", "1,", "a,b", "1,2,3", "-1,0", "300,0"].iter() {
+            assert_eq!(decode_move(line), None);
+        }
+    }
+
+    /// Waits a little for the next move of the other player, unless they left.
+    fn wait_for_move(connection: &Connection) -> Option<PlayingPosition> {
+        for _ in 0..100 {
+            if let Some(position) = connection.receive_move() {
+                return Some(position);
+            }
+            if connection.has_left() {
+                return None;
+            }
+            thread::sleep(std::time::Duration::from_millis(20));
+        }
+        None
+    }
+
+    #[test]
+    fn moves_are_sent_to_the_other_player() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let joined = Connection::join(&address.to_string()).unwrap();
+        let hosted = Connection::new(listener.accept().unwrap().0).unwrap();
+        joined.send_move((1, 2)).unwrap();
+        assert_eq!(wait_for_move(&hosted), Some((1, 2)));
+        hosted.send_move((0, 0)).unwrap();
+        assert_eq!(wait_for_move(&joined), Some((0, 0)));
+        drop(joined);
+        assert_eq!(wait_for_move(&hosted), None);
+        assert!(hosted.has_left());
+    }
+}
@@ -0,0 +1,17 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::cell::RefCell;
+
+thread_local! {
+    /// The source of all the random choices of the game: who starts, and the spots played by the computers.
+    static SOURCE: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Gets a random number between `low` (included) and `high` (excluded).
+pub fn gen_range(low: usize, high: usize) -> usize {
+    SOURCE.with(|source| source.borrow_mut().gen_range(low, high))
+}
+
+/// Makes the random choices follow the given seed from now on, so that the same seed plays the same games.
+pub fn seed(seed: u64) {
+    SOURCE.with(|source| *source.borrow_mut() = StdRng::seed_from_u64(seed));
+}
@@ -1,9 +1,14 @@
-use crossterm::{ClearType, ErrorKind, Terminal, TerminalColor, TerminalCursor};
+use crossterm::{Attribute, ClearType, ErrorKind, Terminal, TerminalColor, TerminalCursor};
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    io::Write,
+};
 
 pub type CursorPosition = (u16, u16);
 pub type ScreenSize = (u16, u16);
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 /// Terminal colors.
 pub enum Color {
     Black,
@@ -26,14 +31,40 @@ pub enum Color {
 }
 
 /// Errors that can occure while rendering to the terminal.
+#[derive(Debug)]
 pub enum Error {
     Fmt(std::fmt::Error),
     Io(std::io::Error),
     Other(String),
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Fmt(e) => write!(f, "could not format the output: {}", e),
+            Error::Io(e) => write!(f, "could not write to the terminal: {}", e),
+            Error::Other(message) => write!(f, "could not render: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Fmt(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Other(_) => None,
+        }
+    }
+}
+
 /// Represents a generic terminal renderer.
 pub trait Renderer {
+    /// Rings the terminal bell. Renderers without a bell do nothing.
+    fn bell(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Clears all lines.
     fn clear(&self) -> Result<(), Error>;
 
@@ -46,23 +77,557 @@ pub trait Renderer {
     /// Gets the size of the terminal.
     fn get_screen_size(&self) -> ScreenSize;
 
+    /// Indicates whether colors and text attributes are shown. If not, setting them does nothing.
+    fn has_colors(&self) -> bool {
+        true
+    }
+
     /// Sets the background color of the terminal.
     fn set_background_color(&self, color: Color) -> Result<(), Error>;
 
+    /// Turns bold text on or off.
+    fn set_bold(&self, on: bool) -> Result<(), Error>;
+
     /// Moves the cursor to the given coordinates.
     fn set_cursor_position(&self, position: CursorPosition) -> Result<(), Error>;
 
     /// Sets the color of the characters in the terminal.
     fn set_foreground_color(&self, color: Color) -> Result<(), Error>;
 
+    /// Turns underlined text on or off.
+    fn set_underline(&self, on: bool) -> Result<(), Error>;
+
     /// Writes some text to the terminal.
     fn write(&self, value: &str) -> Result<usize, Error>;
 }
 
+/// A renderer that draws into memory instead of the terminal. Used to measure what rendering code produces, and to inspect it in tests.
+pub struct BufferRenderer {
+    /// The background colors set so far, each with the length of the contents when it was set.
+    background_colors: RefCell<Vec<(usize, Color)>>,
+    bell_count: Cell<usize>,
+    bold: Cell<bool>,
+    contents: RefCell<String>,
+    cursor_position: Cell<CursorPosition>,
+    /// The foreground colors set so far, each with the length of the contents when it was set.
+    foreground_colors: RefCell<Vec<(usize, Color)>>,
+    has_colors: bool,
+    screen_size: ScreenSize,
+    underline: Cell<bool>,
+}
+
+impl BufferRenderer {
+    /// Constructs a new empty buffer that pretends to be a terminal of the given size.
+    pub fn new(screen_size: ScreenSize) -> Self {
+        BufferRenderer {
+            background_colors: RefCell::new(Vec::new()),
+            bell_count: Cell::new(0),
+            bold: Cell::new(false),
+            contents: RefCell::new(String::new()),
+            cursor_position: Cell::new((0, 0)),
+            foreground_colors: RefCell::new(Vec::new()),
+            has_colors: true,
+            screen_size,
+            underline: Cell::new(false),
+        }
+    }
+
+    /// Constructs a new empty buffer that pretends to be a terminal of the given size, which does not show colors: setting them or text attributes does nothing.
+    pub fn without_colors(screen_size: ScreenSize) -> Self {
+        BufferRenderer {
+            has_colors: false,
+            ..BufferRenderer::new(screen_size)
+        }
+    }
+
+    /// Constructs a new empty buffer that pretends to be the given renderer: of the same size, and showing colors only if it does. Used to measure what will be drawn through that renderer.
+    pub fn like(renderer: &dyn Renderer) -> Self {
+        if renderer.has_colors() {
+            BufferRenderer::new(renderer.get_screen_size())
+        } else {
+            BufferRenderer::without_colors(renderer.get_screen_size())
+        }
+    }
+
+    /// Gets the size of the text written since the last clear: the width of its widest line, and its number of lines.
+    pub fn get_contents_size(&self) -> ScreenSize {
+        let contents = self.contents.borrow();
+        let width = contents.lines().map(|l| l.chars().count()).max();
+        (width.unwrap_or(0) as u16, contents.lines().count() as u16)
+    }
+
+    /// Cuts the contents down to the given length, keeping the colors in effect at its end.
+    fn truncate(&self, length: usize) {
+        self.contents.borrow_mut().truncate(length);
+        for colors in [&self.background_colors, &self.foreground_colors].iter() {
+            for (offset, _) in colors.borrow_mut().iter_mut() {
+                *offset = (*offset).min(length);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl BufferRenderer {
+    /// Gets all the text written since the last clear.
+    pub fn contents(&self) -> std::cell::Ref<'_, str> {
+        std::cell::Ref::map(self.contents.borrow(), |c| c.as_str())
+    }
+
+    /// Gets the last background color that was set.
+    pub fn get_background_color(&self) -> Color {
+        self.get_background_color_at(usize::MAX)
+    }
+
+    /// Gets the background color in which the contents were written at the given byte offset.
+    pub fn get_background_color_at(&self, offset: usize) -> Color {
+        BufferRenderer::get_color_at(&self.background_colors.borrow(), offset)
+    }
+
+    /// Gets the number of times the bell was rung.
+    pub fn get_bell_count(&self) -> usize {
+        self.bell_count.get()
+    }
+
+    /// Gets the last foreground color that was set.
+    pub fn get_foreground_color(&self) -> Color {
+        self.get_foreground_color_at(usize::MAX)
+    }
+
+    /// Gets the foreground color in which the contents were written at the given byte offset.
+    pub fn get_foreground_color_at(&self, offset: usize) -> Color {
+        BufferRenderer::get_color_at(&self.foreground_colors.borrow(), offset)
+    }
+
+    /// Gets the last of the given colors that was set at or before the given byte offset.
+    fn get_color_at(colors: &[(usize, Color)], offset: usize) -> Color {
+        colors
+            .iter()
+            .rev()
+            .find(|(o, _)| *o <= offset)
+            .map_or(Color::Reset, |(_, c)| c.clone())
+    }
+
+    /// Indicates whether bold text is currently on.
+    pub fn is_bold(&self) -> bool {
+        self.bold.get()
+    }
+
+    /// Indicates whether underlined text is currently on.
+    pub fn is_underlined(&self) -> bool {
+        self.underline.get()
+    }
+}
+
+impl Renderer for BufferRenderer {
+    fn bell(&self) -> Result<(), Error> {
+        self.bell_count.set(self.bell_count.get() + 1);
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        self.truncate(0);
+        self.cursor_position.set((0, 0));
+        Ok(())
+    }
+
+    fn clear_current_line(&self) -> Result<(), Error> {
+        let line_start = self.contents.borrow().rfind('\n').map_or(0, |i| i + 1);
+        self.truncate(line_start);
+        self.cursor_position.set((0, self.cursor_position.get().1));
+        Ok(())
+    }
+
+    fn get_cursor_position(&self) -> CursorPosition {
+        self.cursor_position.get()
+    }
+
+    fn get_screen_size(&self) -> ScreenSize {
+        self.screen_size
+    }
+
+    fn has_colors(&self) -> bool {
+        self.has_colors
+    }
+
+    fn set_background_color(&self, color: Color) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
+        let offset = self.contents.borrow().len();
+        self.background_colors.borrow_mut().push((offset, color));
+        Ok(())
+    }
+
+    fn set_bold(&self, on: bool) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
+        self.bold.set(on);
+        Ok(())
+    }
+
+    fn set_cursor_position(&self, position: CursorPosition) -> Result<(), Error> {
+        self.cursor_position.set(position);
+        Ok(())
+    }
+
+    fn set_foreground_color(&self, color: Color) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
+        let offset = self.contents.borrow().len();
+        self.foreground_colors.borrow_mut().push((offset, color));
+        Ok(())
+    }
+
+    fn set_underline(&self, on: bool) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
+        self.underline.set(on);
+        Ok(())
+    }
+
+    fn write(&self, value: &str) -> Result<usize, Error> {
+        let (mut x, mut y) = self.cursor_position.get();
+        for c in value.chars() {
+            if c == '\n' {
+                x = 0;
+                y += 1;
+            } else {
+                x += 1;
+            }
+        }
+        self.cursor_position.set((x, y));
+        self.contents.borrow_mut().push_str(value);
+        Ok(value.len())
+    }
+}
+
+/// A renderer that draws through another one, shifted by an offset: its origin is drawn at the given position of the other renderer, and every new line starts back at the column of the origin.
+pub struct OffsetRenderer<'a> {
+    /// The position of the cursor, relative to the origin.
+    cursor_position: Cell<CursorPosition>,
+    inner: &'a dyn Renderer,
+    origin: CursorPosition,
+}
+
+impl<'a> OffsetRenderer<'a> {
+    /// Constructs a renderer drawing through the given one, with its origin at the given position.
+    pub fn new(inner: &'a dyn Renderer, origin: CursorPosition) -> Self {
+        OffsetRenderer {
+            cursor_position: Cell::new((0, 0)),
+            inner,
+            origin,
+        }
+    }
+}
+
+impl<'a> Renderer for OffsetRenderer<'a> {
+    fn bell(&self) -> Result<(), Error> {
+        self.inner.bell()
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        self.inner.clear()?;
+        self.set_cursor_position((0, 0))
+    }
+
+    fn clear_current_line(&self) -> Result<(), Error> {
+        self.inner.clear_current_line()?;
+        self.set_cursor_position((0, self.cursor_position.get().1))
+    }
+
+    fn get_cursor_position(&self) -> CursorPosition {
+        self.cursor_position.get()
+    }
+
+    fn get_screen_size(&self) -> ScreenSize {
+        self.inner.get_screen_size()
+    }
+
+    fn has_colors(&self) -> bool {
+        self.inner.has_colors()
+    }
+
+    fn set_background_color(&self, color: Color) -> Result<(), Error> {
+        self.inner.set_background_color(color)
+    }
+
+    fn set_bold(&self, on: bool) -> Result<(), Error> {
+        self.inner.set_bold(on)
+    }
+
+    fn set_cursor_position(&self, position: CursorPosition) -> Result<(), Error> {
+        self.cursor_position.set(position);
+        self.inner
+            .set_cursor_position((self.origin.0 + position.0, self.origin.1 + position.1))
+    }
+
+    fn set_foreground_color(&self, color: Color) -> Result<(), Error> {
+        self.inner.set_foreground_color(color)
+    }
+
+    fn set_underline(&self, on: bool) -> Result<(), Error> {
+        self.inner.set_underline(on)
+    }
+
+    fn write(&self, value: &str) -> Result<usize, Error> {
+        for (i, line) in value.split('\n').enumerate() {
+            if i > 0 {
+                self.set_cursor_position((0, self.cursor_position.get().1 + 1))?;
+            }
+            if !line.is_empty() {
+                self.inner.write(line)?;
+                let (x, y) = self.cursor_position.get();
+                self.cursor_position
+                    .set((x + line.chars().count() as u16, y));
+            }
+        }
+        Ok(value.len())
+    }
+}
+
+/// A renderer that draws through a primary renderer, and mirrors everything on a secondary one, such as a `WriteRenderer` streaming to spectators. The secondary renderer follows the layout of the primary one, and its errors are ignored so that a spectator leaving does not stop the game.
+pub struct MirrorRenderer<'a> {
+    primary: &'a dyn Renderer,
+    secondary: &'a dyn Renderer,
+}
+
+impl<'a> MirrorRenderer<'a> {
+    /// Constructs a renderer drawing through the first renderer, mirrored on the second one.
+    pub fn new(primary: &'a dyn Renderer, secondary: &'a dyn Renderer) -> Self {
+        MirrorRenderer { primary, secondary }
+    }
+}
+
+impl<'a> Renderer for MirrorRenderer<'a> {
+    fn bell(&self) -> Result<(), Error> {
+        let _ = self.secondary.bell();
+        self.primary.bell()
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        let _ = self.secondary.clear();
+        self.primary.clear()
+    }
+
+    fn clear_current_line(&self) -> Result<(), Error> {
+        let _ = self.secondary.clear_current_line();
+        self.primary.clear_current_line()
+    }
+
+    fn get_cursor_position(&self) -> CursorPosition {
+        self.primary.get_cursor_position()
+    }
+
+    fn get_screen_size(&self) -> ScreenSize {
+        self.primary.get_screen_size()
+    }
+
+    fn has_colors(&self) -> bool {
+        self.primary.has_colors()
+    }
+
+    fn set_background_color(&self, color: Color) -> Result<(), Error> {
+        let _ = self.secondary.set_background_color(color.clone());
+        self.primary.set_background_color(color)
+    }
+
+    fn set_bold(&self, on: bool) -> Result<(), Error> {
+        let _ = self.secondary.set_bold(on);
+        self.primary.set_bold(on)
+    }
+
+    fn set_cursor_position(&self, position: CursorPosition) -> Result<(), Error> {
+        let _ = self.secondary.set_cursor_position(position);
+        self.primary.set_cursor_position(position)
+    }
+
+    fn set_foreground_color(&self, color: Color) -> Result<(), Error> {
+        let _ = self.secondary.set_foreground_color(color.clone());
+        self.primary.set_foreground_color(color)
+    }
+
+    fn set_underline(&self, on: bool) -> Result<(), Error> {
+        let _ = self.secondary.set_underline(on);
+        self.primary.set_underline(on)
+    }
+
+    fn write(&self, value: &str) -> Result<usize, Error> {
+        let _ = self.secondary.write(value);
+        self.primary.write(value)
+    }
+}
+
+/// A renderer that streams what it draws to any writer, such as a file or a socket. It either sends ANSI escape sequences for colors, text attributes and cursor moves, or only plain text.
+pub struct WriteRenderer<W: Write> {
+    cursor_position: Cell<CursorPosition>,
+    has_colors: bool,
+    output: RefCell<W>,
+    screen_size: ScreenSize,
+}
+
+impl<W: Write> WriteRenderer<W> {
+    /// Constructs a renderer writing plain text to the given output, which pretends to be a terminal of the given size. Colors and text attributes are left out, and the cursor only moves forward, with new lines and spaces: moving it back up or left starts a new line, on which the text is written at the column of the cursor.
+    pub fn new(output: W, screen_size: ScreenSize) -> Self {
+        WriteRenderer {
+            cursor_position: Cell::new((0, 0)),
+            has_colors: false,
+            output: RefCell::new(output),
+            screen_size,
+        }
+    }
+
+    /// Constructs a renderer writing text and ANSI escape sequences to the given output, which pretends to be a terminal of the given size.
+    pub fn with_ansi(output: W, screen_size: ScreenSize) -> Self {
+        WriteRenderer {
+            has_colors: true,
+            ..WriteRenderer::new(output, screen_size)
+        }
+    }
+
+    /// Sends the given text to the output right away.
+    fn emit(&self, value: &str) -> Result<(), Error> {
+        let mut output = self.output.borrow_mut();
+        output.write_all(value.as_bytes()).map_err(Error::Io)?;
+        output.flush().map_err(Error::Io)
+    }
+
+    /// Sends the given ANSI escape sequence, unless only plain text is written.
+    fn emit_ansi(&self, sequence: &str) -> Result<(), Error> {
+        if self.has_colors {
+            self.emit(&format!("\x1b[{}", sequence))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<W: Write> WriteRenderer<W> {
+    /// Gets the output back, once rendering is done.
+    pub fn into_inner(self) -> W {
+        self.output.into_inner()
+    }
+}
+
+impl<W: Write> Renderer for WriteRenderer<W> {
+    fn bell(&self) -> Result<(), Error> {
+        self.emit("\x07")
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        if self.has_colors {
+            self.emit_ansi("2J")?;
+            self.emit_ansi("H")?;
+        } else if self.cursor_position.get() != (0, 0) {
+            // Plain text can't be erased, so the frames are only separated.
+            self.emit("\n\n")?;
+        }
+        self.cursor_position.set((0, 0));
+        Ok(())
+    }
+
+    fn clear_current_line(&self) -> Result<(), Error> {
+        self.emit_ansi("2K")?;
+        self.set_cursor_position((0, self.cursor_position.get().1))
+    }
+
+    fn get_cursor_position(&self) -> CursorPosition {
+        self.cursor_position.get()
+    }
+
+    fn get_screen_size(&self) -> ScreenSize {
+        self.screen_size
+    }
+
+    fn has_colors(&self) -> bool {
+        self.has_colors
+    }
+
+    fn set_background_color(&self, color: Color) -> Result<(), Error> {
+        self.emit_ansi(&format!("{}m", color.get_ansi_code() + 10))
+    }
+
+    fn set_bold(&self, on: bool) -> Result<(), Error> {
+        self.emit_ansi(if on { "1m" } else { "22m" })
+    }
+
+    fn set_cursor_position(&self, position: CursorPosition) -> Result<(), Error> {
+        let (x, y) = self.cursor_position.get();
+        if self.has_colors {
+            self.emit_ansi(&format!("{};{}H", position.1 + 1, position.0 + 1))?;
+        } else if position.1 > y {
+            self.emit(&format!(
+                "{}{}",
+                "\n".repeat((position.1 - y) as usize),
+                " ".repeat(position.0 as usize)
+            ))?;
+        } else if position.1 == y && position.0 > x {
+            self.emit(&" ".repeat((position.0 - x) as usize))?;
+        } else if position != (x, y) {
+            // Plain text can't be overwritten, so what is drawn further back goes below instead of being lost.
+            self.emit(&format!("\n{}", " ".repeat(position.0 as usize)))?;
+        }
+        self.cursor_position.set(position);
+        Ok(())
+    }
+
+    fn set_foreground_color(&self, color: Color) -> Result<(), Error> {
+        self.emit_ansi(&format!("{}m", color.get_ansi_code()))
+    }
+
+    fn set_underline(&self, on: bool) -> Result<(), Error> {
+        self.emit_ansi(if on { "4m" } else { "24m" })
+    }
+
+    fn write(&self, value: &str) -> Result<usize, Error> {
+        let (mut x, mut y) = self.cursor_position.get();
+        for c in value.chars() {
+            if c == '\n' {
+                x = 0;
+                y += 1;
+            } else {
+                x += 1;
+            }
+        }
+        self.cursor_position.set((x, y));
+        // Terminals in raw mode do not go back to the start of the line on their own.
+        if self.has_colors {
+            self.emit(&value.replace('\n', "\r\n"))?;
+        } else {
+            self.emit(value)?;
+        }
+        Ok(value.len())
+    }
+}
+
+/// Gets the position at which content of the given size is drawn so that it is centered on a screen of the given size. Content larger than the screen starts on its edge.
+pub fn get_centering_offset(screen_size: ScreenSize, content_size: ScreenSize) -> CursorPosition {
+    (
+        screen_size.0.saturating_sub(content_size.0) / 2,
+        screen_size.1.saturating_sub(content_size.1) / 2,
+    )
+}
+
+/// Draws some content centered on the screen of the given renderer. The content is drawn off screen first to measure it, with or without colors as the renderer, since the layout can depend on it. Returns the position at which it was drawn.
+pub fn render_centered<F>(renderer: &dyn Renderer, render: F) -> Result<CursorPosition, Error>
+where
+    F: Fn(&dyn Renderer) -> Result<(), Error>,
+{
+    let buffer = BufferRenderer::like(renderer);
+    render(&buffer)?;
+    let origin = get_centering_offset(renderer.get_screen_size(), buffer.get_contents_size());
+    render(&OffsetRenderer::new(renderer, origin))?;
+    Ok(origin)
+}
+
 /// A terminal renderer that uses the crossterm crate.
 pub struct CrosstermRenderer {
     colored_terminal: TerminalColor,
     cursor: TerminalCursor,
+    has_colors: bool,
     terminal: Terminal,
 }
 
@@ -72,22 +637,33 @@ impl CrosstermRenderer {
         CrosstermRenderer {
             colored_terminal: TerminalColor::new(),
             cursor: TerminalCursor::new(),
+            has_colors: true,
             terminal: Terminal::new(),
         }
     }
+
+    /// Constructs a crossterm based renderer for terminals that do not understand colors: it never sends colors nor text attributes, only text and cursor moves.
+    pub fn without_colors() -> Self {
+        CrosstermRenderer {
+            has_colors: false,
+            ..CrosstermRenderer::new()
+        }
+    }
 }
 
 impl Renderer for CrosstermRenderer {
+    fn bell(&self) -> Result<(), Error> {
+        self.terminal.write("\x07").map(|_| ()).map_err(Error::from)
+    }
+
     fn clear(&self) -> Result<(), Error> {
-        self.terminal
-            .clear(ClearType::All)
-            .map_err(|e| Error::from(e))
+        self.terminal.clear(ClearType::All).map_err(Error::from)
     }
 
     fn clear_current_line(&self) -> Result<(), Error> {
         self.terminal
             .clear(ClearType::CurrentLine)
-            .map_err(|e| Error::from(e))
+            .map_err(Error::from)
     }
 
     fn get_cursor_position(&self) -> CursorPosition {
@@ -98,26 +674,89 @@ impl Renderer for CrosstermRenderer {
         self.terminal.terminal_size()
     }
 
+    fn has_colors(&self) -> bool {
+        self.has_colors
+    }
+
     fn set_background_color(&self, color: Color) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
         self.colored_terminal
             .set_bg(crossterm::Color::from(color))
-            .map_err(|e| Error::from(e))
+            .map_err(Error::from)
+    }
+
+    fn set_bold(&self, on: bool) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
+        self.terminal
+            .write(if on {
+                Attribute::Bold
+            } else {
+                Attribute::NormalIntensity
+            })
+            .map(|_| ())
+            .map_err(Error::from)
     }
 
     fn set_cursor_position(&self, position: CursorPosition) -> Result<(), Error> {
         self.cursor
             .goto(position.0, position.1)
-            .map_err(|e| Error::from(e))
+            .map_err(Error::from)
     }
 
     fn set_foreground_color(&self, color: Color) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
         self.colored_terminal
             .set_fg(crossterm::Color::from(color))
-            .map_err(|e| Error::from(e))
+            .map_err(Error::from)
+    }
+
+    fn set_underline(&self, on: bool) -> Result<(), Error> {
+        if !self.has_colors {
+            return Ok(());
+        }
+        self.terminal
+            .write(if on {
+                Attribute::Underlined
+            } else {
+                Attribute::NoUnderline
+            })
+            .map(|_| ())
+            .map_err(Error::from)
     }
 
     fn write(&self, value: &str) -> Result<usize, Error> {
-        self.terminal.write(value).map_err(|e| Error::from(e))
+        self.terminal.write(value).map_err(Error::from)
+    }
+}
+
+impl Color {
+    /// Gets the ANSI code that sets this color as the foreground color. The background code is 10 more.
+    fn get_ansi_code(&self) -> u8 {
+        match self {
+            Color::Black => 30,
+            Color::DarkRed => 31,
+            Color::DarkGreen => 32,
+            Color::DarkYellow => 33,
+            Color::DarkBlue => 34,
+            Color::DarkMagenta => 35,
+            Color::DarkCyan => 36,
+            Color::Grey => 37,
+            Color::Reset => 39,
+            Color::DarkGrey => 90,
+            Color::Red => 91,
+            Color::Green => 92,
+            Color::Yellow => 93,
+            Color::Blue => 94,
+            Color::Magenta => 95,
+            Color::Cyan => 96,
+            Color::White => 97,
+        }
     }
 }
 
@@ -155,3 +794,447 @@ impl From<ErrorKind> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::{
+            board::{Board, Tile, TileTheme},
+            players::{HumanPlayerController, Player, ScriptedPlayerController},
+        },
+        input::{InputEvent, Key, KeyBindings},
+        menus::MenuTheme,
+        scores::SharedScoreboard,
+        settings::{Settings, SharedSettings},
+        states::{playing_state::PlayingState, State, StateManager},
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn buffer_renderer_draws_a_board() {
+        let mut board = Board::new();
+        let _ = board.set(1, 1, Tile::X);
+        let _ = board.set(0, 2, Tile::O);
+        let renderer = BufferRenderer::new((80, 24));
+        board.render(&renderer, &TileTheme::default()).ok().unwrap();
+        assert_eq!(&*renderer.contents(), " | | \n-+-+-\n |x| \n-+-+-\no| | ");
+        assert_eq!(renderer.get_contents_size(), (5, 5));
+        assert_eq!(renderer.get_cursor_position(), (5, 4));
+    }
+
+    #[test]
+    fn write_renderer_streams_a_board() {
+        let mut board = Board::new();
+        let _ = board.set(0, 0, Tile::O);
+        let renderer = WriteRenderer::new(Vec::new(), (80, 24));
+        board.render(&renderer, &TileTheme::default()).ok().unwrap();
+        // Without colors, the cells leave room for the brackets around the winning line.
+        assert_eq!(
+            String::from_utf8(renderer.into_inner()).unwrap(),
+            " o |   |   \n---+---+---\n   | _ |   \n---+---+---\n   |   |   "
+        );
+    }
+
+    #[test]
+    fn write_renderer_sends_ansi_sequences() {
+        let renderer = WriteRenderer::with_ansi(Vec::new(), (80, 24));
+        renderer.set_foreground_color(Color::Red).ok().unwrap();
+        renderer.write("x").ok().unwrap();
+        renderer.set_foreground_color(Color::Reset).ok().unwrap();
+        assert_eq!(
+            String::from_utf8(renderer.into_inner()).unwrap(),
+            format!(
+                "\x1b[{}mx\x1b[{}m",
+                Color::Red.get_ansi_code(),
+                Color::Reset.get_ansi_code()
+            )
+        );
+    }
+
+    #[test]
+    fn buffer_renderer_tracks_attributes() {
+        let renderer = BufferRenderer::new((80, 24));
+        renderer.set_foreground_color(Color::Red).ok().unwrap();
+        renderer.set_background_color(Color::Blue).ok().unwrap();
+        renderer.set_bold(true).ok().unwrap();
+        renderer.set_underline(true).ok().unwrap();
+        renderer.bell().ok().unwrap();
+        assert!(renderer.get_foreground_color() == Color::Red);
+        assert!(renderer.get_background_color() == Color::Blue);
+        assert!(renderer.is_bold() && renderer.is_underlined());
+        assert_eq!(renderer.get_bell_count(), 1);
+        renderer.write("line\n").ok().unwrap();
+        renderer.clear().ok().unwrap();
+        assert_eq!(&*renderer.contents(), "");
+        assert_eq!(renderer.get_cursor_position(), (0, 0));
+    }
+
+    #[test]
+    fn errors_are_described() {
+        use std::error::Error as _;
+        let errors = [
+            Error::Fmt(fmt::Error),
+            Error::Io(std::io::Error::other("closed")),
+            Error::Other("no terminal".to_string()),
+        ];
+        for e in errors.iter() {
+            assert!(!e.to_string().is_empty());
+        }
+        assert_eq!(
+            errors[1].to_string(),
+            "could not write to the terminal: closed"
+        );
+        assert!(errors[1].source().is_some());
+        assert!(errors[2].source().is_none());
+    }
+
+    #[test]
+    fn unicode_theme_keeps_the_grid_aligned() {
+        let mut board = Board::new();
+        let _ = board.set(1, 1, Tile::X);
+        let _ = board.set(0, 2, Tile::O);
+        let renderer = BufferRenderer::new((80, 24));
+        board.render(&renderer, &TileTheme::unicode()).ok().unwrap();
+        assert_eq!(&*renderer.contents(), " | | \n-+-+-\n |✕| \n-+-+-\n◯| | ");
+        // The glyphs take more than one byte, but every line is as wide as the separators.
+        assert!(renderer.contents().lines().all(|l| l.chars().count() == 5));
+        assert_eq!(renderer.get_contents_size(), (5, 5));
+    }
+
+    /// Two human players, x and o.
+    fn human_players() -> Vec<Player> {
+        vec![
+            Player::new(
+                Box::new(HumanPlayerController::new(KeyBindings::default())),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(HumanPlayerController::new(KeyBindings::default())),
+                Tile::O,
+            ),
+        ]
+    }
+
+    #[test]
+    fn current_turn_is_drawn_in_the_color_of_the_player() {
+        let game = PlayingState::with_players_starting(
+            human_players(),
+            0,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        );
+        let renderer = BufferRenderer::new((80, 24));
+        game.render(&renderer).ok().unwrap();
+        let tile = renderer.contents().find("It's x").unwrap() + "It's ".len();
+        assert!(renderer.get_foreground_color_at(tile) == TileTheme::default().x_color);
+        assert!(renderer.get_foreground_color_at(tile - 1) == Color::Reset);
+        assert!(renderer.get_foreground_color_at(tile + 1) == Color::Reset);
+    }
+
+    #[test]
+    fn winning_line_is_drawn_in_the_color_of_the_theme() {
+        let mut board = Board::new();
+        for x in 0..3 {
+            let _ = board.set(x, 0, Tile::X);
+        }
+        board.highlight_solutions(vec![vec![(0, 0), (1, 0), (2, 0)]]);
+        let theme = TileTheme {
+            highlight: Color::Blue,
+            ..TileTheme::default()
+        };
+        let renderer = BufferRenderer::new((80, 24));
+        board.render(&renderer, &theme).ok().unwrap();
+        assert!(renderer.contents().starts_with("x|x|x\n"));
+        for tile in [0, 2, 4].iter() {
+            assert!(renderer.get_background_color_at(*tile) == Color::Blue);
+        }
+        // The background goes back to the default of the terminal, not to black.
+        assert!(renderer.get_background_color_at(1) == Color::Reset);
+        assert!(renderer.get_background_color() == Color::Reset);
+    }
+
+    #[test]
+    fn highlighted_tile_sets_and_resets_both_colors() {
+        let mut board = Board::new();
+        let _ = board.set(1, 1, Tile::O);
+        board.highlight_solutions(vec![vec![(1, 1)]]);
+        let theme = TileTheme {
+            highlight_foreground: Color::Black,
+            ..TileTheme::default()
+        };
+        let renderer = BufferRenderer::new((80, 24));
+        board.render(&renderer, &theme).ok().unwrap();
+        let tile = renderer.contents().find('o').unwrap();
+        let colors_at = |offset| {
+            (
+                renderer.get_background_color_at(offset),
+                renderer.get_foreground_color_at(offset),
+            )
+        };
+        assert!(colors_at(tile - 1) == (Color::Reset, Color::Reset));
+        assert!(colors_at(tile) == (Color::Green, Color::Black));
+        assert!(colors_at(tile + 1) == (Color::Reset, Color::Reset));
+    }
+
+    /// An output that only accepts escape sequences, as if the terminal went away while drawing a tile.
+    struct TextRejectingWriter(Vec<u8>);
+
+    impl Write for TextRejectingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if !buf.starts_with(b"\x1b") {
+                return Err(std::io::Error::other("closed"));
+            }
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn highlight_is_reset_when_a_tile_can_not_be_drawn() {
+        let mut board = Board::new();
+        board.highlight_solutions(vec![vec![(0, 0)]]);
+        let renderer = WriteRenderer::with_ansi(TextRejectingWriter(Vec::new()), (80, 24));
+        assert!(board.render(&renderer, &TileTheme::default()).is_err());
+        let output = String::from_utf8(renderer.into_inner().0).unwrap();
+        assert!(output.ends_with(&format!(
+            "\x1b[{}m\x1b[{}m",
+            Color::Reset.get_ansi_code() + 10,
+            Color::Reset.get_ansi_code()
+        )));
+    }
+
+    #[test]
+    fn content_is_centered_on_the_screen() {
+        assert_eq!(get_centering_offset((80, 24), (5, 5)), (37, 9));
+        assert_eq!(get_centering_offset((4, 3), (5, 5)), (0, 0));
+        let board = Board::new();
+        let render = |r: &dyn Renderer| board.render(r, &TileTheme::default());
+        let renderer = BufferRenderer::new((80, 24));
+        assert_eq!(render_centered(&renderer, render).ok().unwrap(), (37, 9));
+        assert_eq!(renderer.get_cursor_position(), (37 + 5, 9 + 4));
+        // Without colors, the cells are wider to leave room for the brackets.
+        let renderer = BufferRenderer::without_colors((80, 24));
+        assert_eq!(render_centered(&renderer, render).ok().unwrap(), (34, 9));
+        assert_eq!(renderer.get_cursor_position(), (34 + 11, 9 + 4));
+    }
+
+    /// Plays a game between two scripted players with the given settings, x winning on the top row, and returns the state manager showing its result.
+    fn finished_game(settings: Settings) -> StateManager {
+        let players = vec![
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(0, 0), (1, 0), (2, 0)])),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(0, 1), (1, 1)])),
+                Tile::O,
+            ),
+        ];
+        let game = PlayingState::with_players_starting(
+            players,
+            0,
+            SharedScoreboard::default(),
+            Rc::new(RefCell::new(settings)),
+        );
+        let mut state_manager = StateManager::new(Box::new(game));
+        for _ in 0..5 {
+            state_manager.tick();
+        }
+        assert!(state_manager.take_game_outcome().is_some());
+        state_manager
+    }
+
+    #[test]
+    fn high_contrast_mode_draws_the_selection_and_the_winning_line_black_on_white() {
+        let settings = Settings {
+            animate_win: false,
+            arrow_key_menus: true,
+            high_contrast: true,
+            menu_theme: MenuTheme::high_contrast(),
+            tile_theme: TileTheme::default().with_high_contrast(),
+            ..Settings::default()
+        };
+        let renderer = BufferRenderer::new((80, 50));
+        finished_game(settings).render(&renderer).ok().unwrap();
+        let contents = renderer.contents();
+        let colors_at = |offset| {
+            (
+                renderer.get_background_color_at(offset),
+                renderer.get_foreground_color_at(offset),
+            )
+        };
+        // Centered content is drawn line by line, moving the cursor instead of writing new lines.
+        assert!(contents.starts_with("x|x|x-+-+-"));
+        for tile in [0, 2, 4].iter() {
+            assert!(colors_at(*tile) == (Color::White, Color::Black));
+        }
+        // Only computers played, so there is no game to play again, and the next entry is selected.
+        let disabled = contents.find("x) Play again").unwrap();
+        assert!(colors_at(disabled) == (Color::Reset, Color::Yellow));
+        let selected = contents.find("Change opponent").unwrap();
+        assert!(colors_at(selected) == (Color::White, Color::Black));
+        let other = contents.find("Quit").unwrap();
+        assert!(colors_at(other) == (Color::Reset, Color::White));
+    }
+
+    #[test]
+    fn winning_line_is_bracketed_without_colors() {
+        let mut board = Board::new();
+        for x in 0..3 {
+            let _ = board.set(x, 0, Tile::X);
+        }
+        let _ = board.set(0, 1, Tile::O);
+        let _ = board.set(1, 1, Tile::O);
+        board.highlight_solutions(vec![vec![(0, 0), (1, 0), (2, 0)]]);
+        let colored = BufferRenderer::new((80, 24));
+        board.render(&colored, &TileTheme::default()).ok().unwrap();
+        assert_eq!(&*colored.contents(), "x|x|x\n-+-+-\no|o| \n-+-+-\n | | ");
+        assert!(colored.get_background_color_at(0) == Color::Green);
+        let plain = BufferRenderer::without_colors((80, 24));
+        board.render(&plain, &TileTheme::default()).ok().unwrap();
+        assert_eq!(
+            &*plain.contents(),
+            "[x]|[x]|[x]\n---+---+---\n o | o |   \n---+---+---\n   |   |   "
+        );
+        assert!(plain.get_background_color_at(1) == Color::Reset);
+    }
+
+    #[test]
+    fn labels_are_drawn_around_the_grid() {
+        let mut board = Board::new();
+        let _ = board.set(0, 2, Tile::X);
+        let theme = TileTheme {
+            labels: true,
+            ..TileTheme::default()
+        };
+        let renderer = BufferRenderer::new((80, 24));
+        board.render(&renderer, &theme).ok().unwrap();
+        assert_eq!(
+            &*renderer.contents(),
+            "  a b c\n3  | | \n  -+-+-\n2  |_| \n  -+-+-\n1 x| | "
+        );
+        assert_eq!(board.get_position_label((0, 2)), "a1");
+        // The grid, and the cursor with it, is moved past the labels.
+        assert_eq!(board.get_grid_offset(&theme), (2, 1));
+    }
+
+    #[test]
+    fn end_of_the_game_shows_its_statistics() {
+        let renderer = BufferRenderer::new((80, 50));
+        finished_game(Settings::default())
+            .render(&renderer)
+            .ok()
+            .unwrap();
+        let contents = renderer.contents();
+        assert!(contents.contains("x won!"));
+        assert!(contents.contains("Moves played: 5"));
+        assert!(contents.contains("Game length: 0:00"));
+        assert!(contents.contains("First to move: x"));
+    }
+
+    #[test]
+    fn bell_rings_once_when_a_move_is_rejected() {
+        let settings = Settings {
+            bell: true,
+            ..Settings::default()
+        };
+        let game = PlayingState::with_players_starting(
+            human_players(),
+            0,
+            SharedScoreboard::default(),
+            Rc::new(RefCell::new(settings)),
+        );
+        let mut state_manager = StateManager::new(Box::new(game));
+        let renderer = BufferRenderer::new((80, 24));
+        // x plays the center, where the cursor starts.
+        state_manager
+            .handle_input(InputEvent::Key(Key::Char('\n')))
+            .ok()
+            .unwrap();
+        state_manager.render(&renderer).ok().unwrap();
+        assert_eq!(renderer.get_bell_count(), 0);
+        // o tries to play it as well.
+        state_manager
+            .handle_input(InputEvent::Key(Key::Char('\n')))
+            .ok()
+            .unwrap();
+        state_manager.render(&renderer).ok().unwrap();
+        assert_eq!(renderer.get_bell_count(), 1);
+        state_manager.render(&renderer).ok().unwrap();
+        assert_eq!(renderer.get_bell_count(), 1);
+    }
+
+    #[test]
+    fn cursor_marks_the_playing_position_while_it_is_empty() {
+        let mut board = Board::new();
+        board.playing_position = (2, 0);
+        let renderer = BufferRenderer::new((80, 24));
+        board.render(&renderer, &TileTheme::default()).ok().unwrap();
+        assert!(renderer.contents().starts_with(" | |_\n"));
+        let theme = TileTheme {
+            cursor: Some("*".to_string()),
+            ..TileTheme::default()
+        };
+        renderer.clear().ok().unwrap();
+        board.render(&renderer, &theme).ok().unwrap();
+        assert!(renderer.contents().starts_with(" | |*\n"));
+        let _ = board.set(2, 0, Tile::O);
+        renderer.clear().ok().unwrap();
+        board.render(&renderer, &theme).ok().unwrap();
+        assert!(renderer.contents().starts_with(" | |o\n"));
+        assert!(!renderer.contents().contains('*'));
+    }
+
+    #[test]
+    fn plain_write_renderer_starts_a_new_line_to_move_back() {
+        let renderer = WriteRenderer::new(Vec::new(), (80, 24));
+        renderer.write("abc\ndef").ok().unwrap();
+        renderer.set_cursor_position((1, 0)).ok().unwrap();
+        renderer.write("x").ok().unwrap();
+        renderer.set_cursor_position((4, 0)).ok().unwrap();
+        renderer.write("y").ok().unwrap();
+        renderer.set_cursor_position((0, 0)).ok().unwrap();
+        renderer.write("z").ok().unwrap();
+        assert_eq!(renderer.get_cursor_position(), (1, 0));
+        assert_eq!(
+            String::from_utf8(renderer.into_inner()).unwrap(),
+            "abc\ndef\n x  y\nz"
+        );
+    }
+
+    #[test]
+    fn mirror_renderer_reaches_the_secondary_sink() {
+        let mut board = Board::new();
+        let _ = board.set(0, 0, Tile::X);
+        board.highlight_solutions(vec![vec![(0, 0)]]);
+        let primary = BufferRenderer::new((80, 24));
+        let secondary = WriteRenderer::with_ansi(Vec::new(), (80, 24));
+        let mirror = MirrorRenderer::new(&primary, &secondary);
+        board.render(&mirror, &TileTheme::default()).ok().unwrap();
+        assert_eq!(&*primary.contents(), "x| | \n-+-+-\n |_| \n-+-+-\n | | ");
+        assert_eq!(
+            String::from_utf8(secondary.into_inner()).unwrap(),
+            format!(
+                "\x1b[{}m\x1b[{}mx\x1b[{}m\x1b[{}m| | \r\n-+-+-\r\n |_| \r\n-+-+-\r\n | | ",
+                Color::Green.get_ansi_code() + 10,
+                Color::Reset.get_ansi_code(),
+                Color::Reset.get_ansi_code() + 10,
+                Color::Reset.get_ansi_code()
+            )
+        );
+    }
+
+    #[test]
+    fn mirror_renderer_goes_on_without_the_secondary_sink() {
+        let board = Board::new();
+        let primary = BufferRenderer::new((80, 24));
+        let secondary = WriteRenderer::with_ansi(TextRejectingWriter(Vec::new()), (80, 24));
+        let mirror = MirrorRenderer::new(&primary, &secondary);
+        board.render(&mirror, &TileTheme::default()).ok().unwrap();
+        assert_eq!(&*primary.contents(), " | | \n-+-+-\n |_| \n-+-+-\n | | ");
+    }
+}
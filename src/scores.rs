@@ -0,0 +1,201 @@
+use crate::{error::GameError, game::board::Tile};
+use std::{cell::RefCell, fs, rc::Rc};
+
+/// The path of the file in which the lifetime scores are stored.
+pub const SCORES_FILE_PATH: &str = "tictactoe.scores";
+
+/// The count of wins of each kind of tile, accumulated across sessions.
+#[derive(Clone, Default)]
+pub struct LifetimeScores {
+    /// The number of games won by o.
+    pub o: usize,
+    /// The number of games won by x.
+    pub x: usize,
+}
+
+impl LifetimeScores {
+    /// Reads the scores back from the text produced by `to_save_string`.
+    pub fn from_save_string(s: &str) -> Result<LifetimeScores, GameError> {
+        let mut scores = LifetimeScores::default();
+        for line in s.lines().filter(|l| !l.trim().is_empty()) {
+            let mut parts = line.split_whitespace();
+            let tile = parts.next().and_then(|t| t.chars().next());
+            let count = parts
+                .next()
+                .and_then(|c| c.parse::<usize>().ok())
+                .ok_or_else(|| GameError::Parse(format!("malformed score line: {}", line)))?;
+            match tile {
+                Some('o') => scores.o = count,
+                Some('x') => scores.x = count,
+                _ => return Err(GameError::Parse(format!("unknown tile in line: {}", line))),
+            }
+        }
+        Ok(scores)
+    }
+
+    /// Reads the scores from disk. A missing or corrupt file gives fresh scores.
+    pub fn load() -> LifetimeScores {
+        fs::read_to_string(SCORES_FILE_PATH)
+            .map_err(GameError::from)
+            .and_then(|s| LifetimeScores::from_save_string(&s))
+            .unwrap_or_default()
+    }
+
+    /// Counts a win for the given tile.
+    pub fn record_win(&mut self, tile: Tile) {
+        match tile {
+            Tile::O => self.o += 1,
+            Tile::X => self.x += 1,
+            Tile::Empty => {}
+        }
+    }
+
+    /// Writes the scores to disk.
+    pub fn save(&self) -> Result<(), GameError> {
+        fs::write(SCORES_FILE_PATH, self.to_save_string()).map_err(GameError::from)
+    }
+
+    /// Writes the scores as text, one line per kind of tile.
+    pub fn to_save_string(&self) -> String {
+        format!("o {}\nx {}\n", self.o, self.x)
+    }
+}
+
+/// The wins and draws of the games played in a row, whoever the opponent. Unlike the lifetime scores, it is not saved.
+#[derive(Clone, Copy, Default)]
+pub struct Scoreboard {
+    /// The number of games that ended in a draw.
+    pub draws: usize,
+    /// The number of games won by o.
+    pub o: usize,
+    /// The number of games won by x.
+    pub x: usize,
+}
+
+impl Scoreboard {
+    /// Gets the number of games won by the given tile.
+    pub fn get_wins(&self, tile: Tile) -> usize {
+        match tile {
+            Tile::O => self.o,
+            Tile::X => self.x,
+            Tile::Empty => 0,
+        }
+    }
+
+    /// Counts a game won by the given tile, or a draw.
+    pub fn record(&mut self, winner: Option<Tile>) {
+        match winner {
+            Some(Tile::O) => self.o += 1,
+            Some(Tile::X) => self.x += 1,
+            _ => self.draws += 1,
+        }
+    }
+}
+
+/// A series of games, won by the first tile to win most of them.
+#[derive(Clone, Copy)]
+pub struct Match {
+    /// The number of games of the match. A single game is a match of one game.
+    pub best_of: usize,
+    /// The results of the games played so far.
+    pub results: Scoreboard,
+}
+
+impl Match {
+    /// Constructs a match of the given number of games, none of them played yet.
+    pub fn new(best_of: usize) -> Self {
+        Match {
+            best_of,
+            results: Scoreboard::default(),
+        }
+    }
+
+    /// Gets the number of games played so far.
+    pub fn get_games_played(&self) -> usize {
+        self.results.draws + self.results.o + self.results.x
+    }
+
+    /// Gets the tile that won the match, once it is over. `None` if the match is not over, or ended in a tie.
+    pub fn get_winner(&self) -> Option<Tile> {
+        if !self.is_over() {
+            return None;
+        }
+        let (o, x) = (self.results.o, self.results.x);
+        if o > x {
+            Some(Tile::O)
+        } else if x > o {
+            Some(Tile::X)
+        } else {
+            None
+        }
+    }
+
+    /// Indicates whether the match is over: a tile won more than half of the games, or all of them were played.
+    pub fn is_over(&self) -> bool {
+        self.results.o * 2 > self.best_of
+            || self.results.x * 2 > self.best_of
+            || self.get_games_played() >= self.best_of
+    }
+}
+
+/// The scoreboard, shared by the states of a series of games so that it survives a change of opponent.
+pub type SharedScoreboard = Rc<RefCell<Scoreboard>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifetime_scores_round_trip() {
+        let mut scores = LifetimeScores::default();
+        scores.record_win(Tile::O);
+        scores.record_win(Tile::X);
+        scores.record_win(Tile::X);
+        scores.record_win(Tile::Empty);
+        let loaded = LifetimeScores::from_save_string(&scores.to_save_string())
+            .ok()
+            .unwrap();
+        assert_eq!((loaded.o, loaded.x), (1, 2));
+    }
+
+    #[test]
+    fn malformed_lifetime_scores_are_refused() {
+        assert!(LifetimeScores::from_save_string("o one\n").is_err());
+        assert!(LifetimeScores::from_save_string("z 3\n").is_err());
+    }
+
+    #[test]
+    fn scoreboard_counts_draws_apart_from_wins() {
+        let mut scoreboard = Scoreboard::default();
+        scoreboard.record(Some(Tile::X));
+        scoreboard.record(None);
+        scoreboard.record(None);
+        scoreboard.record(Some(Tile::O));
+        assert_eq!(scoreboard.draws, 2);
+        assert_eq!(scoreboard.get_wins(Tile::O), 1);
+        assert_eq!(scoreboard.get_wins(Tile::X), 1);
+        assert_eq!(scoreboard.get_wins(Tile::Empty), 0);
+    }
+
+    #[test]
+    fn match_ends_once_a_tile_won_most_games() {
+        let mut best_of_three = Match::new(3);
+        best_of_three.results.record(Some(Tile::X));
+        assert!(!best_of_three.is_over());
+        assert!(best_of_three.get_winner().is_none());
+        best_of_three.results.record(None);
+        best_of_three.results.record(Some(Tile::X));
+        assert!(best_of_three.is_over());
+        assert!(best_of_three.get_winner() == Some(Tile::X));
+        // A match is over when all its games are played, and tied if no tile won more of them.
+        let mut tied = Match::new(2);
+        tied.results.record(Some(Tile::O));
+        tied.results.record(Some(Tile::X));
+        assert_eq!(tied.get_games_played(), 2);
+        assert!(tied.is_over());
+        assert!(tied.get_winner().is_none());
+        let mut single_game = Match::new(1);
+        single_game.results.record(Some(Tile::O));
+        assert!(single_game.get_winner() == Some(Tile::O));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    game::{
+        board::{Tile, TileTheme},
+        players::OpponentKind,
+    },
+    input::{InputMode, KeyBindings},
+    menus::MenuTheme,
+};
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+/// The user preferences.
+#[derive(Clone)]
+pub struct Settings {
+    /// Indicates whether the winning line is revealed tile by tile when a game ends.
+    pub animate_win: bool,
+    /// Indicates whether menu entries are chosen with the arrow keys rather than by typing their number.
+    pub arrow_key_menus: bool,
+    /// Indicates whether the terminal bell rings when a move is rejected and when a game ends.
+    pub bell: bool,
+    /// Indicates whether `OpponentKind::EasyComputer` leaves out the spots that would let its opponent win on the next turn.
+    pub cautious_easy_computer: bool,
+    /// The difficulty of `OpponentKind::AdjustableComputer`, from 0 to `players::MAX_DIFFICULTY`.
+    pub computer_difficulty: u8,
+    /// Indicates whether pressing escape during a game asks for confirmation before quitting.
+    pub confirm_quit: bool,
+    /// The opponent picked when the user does not choose one explicitly.
+    pub default_opponent: OpponentKind,
+    /// The kind of tile that always moves first, or `None` to pick the first player at random.
+    pub first_mover: Option<Tile>,
+    /// Indicates whether the themes use the high contrast colors. See `MenuTheme::high_contrast` and `TileTheme::with_high_contrast`.
+    pub high_contrast: bool,
+    /// The keys used during a game.
+    pub key_bindings: KeyBindings,
+    /// The number of games of a match, 1 to play single games.
+    pub match_length: usize,
+    /// The colors of the menus, and how the selected entry is drawn when menu entries are chosen with the arrow keys.
+    pub menu_theme: MenuTheme,
+    /// Indicates whether tiles can be played by clicking them.
+    pub mouse: bool,
+    /// Indicates whether only the last move can be undone, before the next player moves, so that a player can't take back the moves of their opponent.
+    pub strict_undo: bool,
+    /// The symbols used to draw the tiles.
+    pub tile_theme: TileTheme,
+    /// The time each player has to play a whole game, or `None` to play without clocks.
+    pub time_limit: Option<Duration>,
+    /// Indicates whether moving the cursor past an edge of the board brings it to the opposite edge.
+    pub wrap_around: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            animate_win: true,
+            arrow_key_menus: false,
+            bell: false,
+            cautious_easy_computer: false,
+            computer_difficulty: 5,
+            confirm_quit: true,
+            default_opponent: OpponentKind::MediumComputer,
+            first_mover: None,
+            high_contrast: false,
+            key_bindings: KeyBindings::default(),
+            match_length: 1,
+            menu_theme: MenuTheme::default(),
+            mouse: false,
+            strict_undo: false,
+            tile_theme: TileTheme::default(),
+            time_limit: None,
+            wrap_around: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Gets the input mode of the menus, following `arrow_key_menus`.
+    pub fn get_menu_input_mode(&self) -> InputMode {
+        if self.arrow_key_menus {
+            InputMode::Key
+        } else {
+            InputMode::Line
+        }
+    }
+}
+
+/// The settings, shared by all the states so that a change is seen everywhere.
+pub type SharedSettings = Rc<RefCell<Settings>>;
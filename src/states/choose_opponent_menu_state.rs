@@ -1,61 +1,167 @@
 use crate::{
-    game::players::{
-        BasicComputerPlayerController, HumanPlayerController, UnbeatableComputerPlayerController,
+    game::{
+        board::Tile,
+        players::{OpponentKind, Player},
     },
-    menus::{Menu, MenuEntry, MenuEntryId, MenuState},
+    input::InputMode,
+    menus::{Menu, MenuEntry, MenuEntryId, MenuState, MenuTheme},
     rendering::{Error, Renderer},
-    states::{playing_state::PlayingState, StateTransition},
+    scores::SharedScoreboard,
+    settings::SharedSettings,
+    states::{
+        choose_tile_menu_state::ChooseTileMenuState,
+        playing_state::{PlayingState, SavedGame},
+        StateTransition,
+    },
 };
 
 /// The menu in which the user chooses its opponent.
 pub struct ChooseOpponentMenuState {
+    against_computer_adjustable_entry: MenuEntryId,
+    against_computer_defensive_entry: MenuEntryId,
     against_computer_easy_entry: MenuEntryId,
+    against_computer_medium_entry: MenuEntryId,
     against_computer_unbeatable_entry: MenuEntryId,
     against_friend_entry: MenuEntryId,
+    /// Lets the user watch a game between two computers.
+    computer_against_computer_entry: MenuEntryId,
     menu: Menu,
+    /// The game to resume once the opponent is chosen, if any.
+    saved_game: Option<SavedGame>,
+    /// The scores of the games played from this menu, kept whatever the opponent.
+    scoreboard: SharedScoreboard,
+    settings: SharedSettings,
 }
 
 impl ChooseOpponentMenuState {
-    pub fn new() -> Self {
+    pub fn new(scoreboard: SharedScoreboard, settings: SharedSettings) -> Self {
+        let default_opponent = settings.borrow().default_opponent;
         let mut menu = Menu::new();
-        let against_friend_entry = menu.push(MenuEntry::new("Against a friend", 1));
-        let against_computer_easy_entry =
-            menu.push(MenuEntry::new("Against the computer (easy)", 2));
+        // The default opponent is marked, and chosen if the user validates without typing anything.
+        let mut push_opponent = |opponent: OpponentKind, key: char| {
+            let mut text = format!("Against {}", opponent.get_description());
+            if opponent == default_opponent {
+                text.push_str(" (default)");
+            }
+            let mut entry = MenuEntry::new(text, key);
+            entry.help = Some(opponent.get_help().to_string());
+            let entry = menu.push(entry);
+            if opponent == default_opponent {
+                menu.set_default(entry);
+            }
+            entry
+        };
+        let against_friend_entry = push_opponent(OpponentKind::Friend, '1');
+        let against_computer_easy_entry = push_opponent(OpponentKind::EasyComputer, '2');
+        let against_computer_medium_entry = push_opponent(OpponentKind::MediumComputer, '3');
         let against_computer_unbeatable_entry =
-            menu.push(MenuEntry::new("Against the computer (unbeatable)", 3));
-        menu.push(MenuEntry::new("Go back", 4));
+            push_opponent(OpponentKind::UnbeatableComputer, '4');
+        let against_computer_defensive_entry = push_opponent(OpponentKind::DefensiveComputer, '5');
+        let against_computer_adjustable_entry =
+            push_opponent(OpponentKind::AdjustableComputer, '6');
+        let computer_against_computer_entry = menu.push(MenuEntry::new(
+            format!(
+                "Watch {} play against {}",
+                OpponentKind::UnbeatableComputer.get_description(),
+                OpponentKind::EasyComputer.get_description()
+            ),
+            '7',
+        ));
+        menu.push(MenuEntry::new("Go back", '8'));
         ChooseOpponentMenuState {
+            against_computer_adjustable_entry,
+            against_computer_defensive_entry,
             against_computer_easy_entry,
+            against_computer_medium_entry,
             against_computer_unbeatable_entry,
             against_friend_entry,
+            computer_against_computer_entry,
             menu,
+            saved_game: None,
+            scoreboard,
+            settings,
+        }
+    }
+
+    /// Constructs the menu so that the chosen opponent resumes the given saved game.
+    pub fn with_saved_game(saved_game: SavedGame, settings: SharedSettings) -> Self {
+        let mut state = ChooseOpponentMenuState::new(SharedScoreboard::default(), settings);
+        state.saved_game = Some(saved_game);
+        // A saved game always has a human player.
+        state
+            .menu
+            .set_visible(state.computer_against_computer_entry, false);
+        state
+    }
+
+    /// Lets the user choose its tile against the given opponent. The saved game, if any, is handed over: it is resumed only once, and the next games from this menu are new ones.
+    fn choose_tile(&mut self, opponent: OpponentKind) -> StateTransition {
+        if self.saved_game.is_some() {
+            self.menu
+                .set_visible(self.computer_against_computer_entry, true);
         }
+        StateTransition::Push(Box::new(ChooseTileMenuState::new(
+            opponent,
+            self.saved_game.take(),
+            self.scoreboard.clone(),
+            self.settings.clone(),
+        )))
+    }
+
+    /// Starts a game between two computers, which the user only watches.
+    fn watch_computers(&self) -> StateTransition {
+        let settings = &self.settings.borrow();
+        let players = vec![
+            Player::new(
+                OpponentKind::UnbeatableComputer.create_controller(Tile::X, settings),
+                Tile::X,
+            ),
+            Player::new(
+                OpponentKind::EasyComputer.create_controller(Tile::O, settings),
+                Tile::O,
+            ),
+        ];
+        StateTransition::Push(Box::new(PlayingState::with_players(
+            players,
+            self.scoreboard.clone(),
+            self.settings.clone(),
+        )))
     }
 }
 
 impl MenuState for ChooseOpponentMenuState {
+    fn get_input_mode(&self) -> InputMode {
+        self.settings.borrow().get_menu_input_mode()
+    }
+
     fn get_menu(&self) -> &Menu {
         &self.menu
     }
 
+    fn get_menu_theme(&self) -> MenuTheme {
+        self.settings.borrow().menu_theme.clone()
+    }
+
     fn handle_selection(&mut self, entry: MenuEntryId) -> StateTransition {
-        if entry == self.against_computer_easy_entry {
-            return StateTransition::Switch(Box::new(PlayingState::with_opponent(Box::new(
-                BasicComputerPlayerController {},
-            ))));
+        if entry == self.against_computer_adjustable_entry {
+            return self.choose_tile(OpponentKind::AdjustableComputer);
+        } else if entry == self.against_computer_defensive_entry {
+            return self.choose_tile(OpponentKind::DefensiveComputer);
+        } else if entry == self.against_computer_easy_entry {
+            return self.choose_tile(OpponentKind::EasyComputer);
+        } else if entry == self.against_computer_medium_entry {
+            return self.choose_tile(OpponentKind::MediumComputer);
         } else if entry == self.against_computer_unbeatable_entry {
-            return StateTransition::Switch(Box::new(PlayingState::with_opponent(Box::new(
-                UnbeatableComputerPlayerController {},
-            ))));
+            return self.choose_tile(OpponentKind::UnbeatableComputer);
         } else if entry == self.against_friend_entry {
-            return StateTransition::Switch(Box::new(PlayingState::with_opponent(Box::new(
-                HumanPlayerController {},
-            ))));
+            return self.choose_tile(OpponentKind::Friend);
+        } else if entry == self.computer_against_computer_entry {
+            return self.watch_computers();
         }
         StateTransition::Pop
     }
 
-    fn render_header(&self, renderer: &Renderer) -> Result<(), Error> {
+    fn render_header(&self, renderer: &dyn Renderer) -> Result<(), Error> {
         renderer.write("Who would you like to play against?\n\n")?;
         Ok(())
     }
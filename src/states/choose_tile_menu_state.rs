@@ -0,0 +1,94 @@
+use crate::{
+    game::{board::Tile, players::OpponentKind},
+    input::InputMode,
+    menus::{Menu, MenuEntry, MenuEntryId, MenuState, MenuTheme},
+    rendering::{Error, Renderer},
+    scores::SharedScoreboard,
+    settings::SharedSettings,
+    states::{
+        playing_state::{PlayingState, SavedGame},
+        StateTransition,
+    },
+};
+
+/// The menu in which the user chooses the kind of tile they will play.
+pub struct ChooseTileMenuState {
+    menu: Menu,
+    o_entry: MenuEntryId,
+    opponent: OpponentKind,
+    /// The game to resume once the tile is chosen, if any.
+    saved_game: Option<SavedGame>,
+    scoreboard: SharedScoreboard,
+    settings: SharedSettings,
+    x_entry: MenuEntryId,
+}
+
+impl ChooseTileMenuState {
+    pub fn new(
+        opponent: OpponentKind,
+        saved_game: Option<SavedGame>,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let mut menu = Menu::new();
+        let o_entry = menu.push(MenuEntry::new("Play o", '1'));
+        let x_entry = menu.push(MenuEntry::new("Play x", '2'));
+        menu.push(MenuEntry::new("Go back", '3'));
+        ChooseTileMenuState {
+            menu,
+            o_entry,
+            opponent,
+            saved_game,
+            scoreboard,
+            settings,
+            x_entry,
+        }
+    }
+
+    /// Replaces this menu with the game, so that the stack holds the main menu, the choice of the opponent and the game. Going back from the game thus leads to the choice of the opponent.
+    fn start_game(&mut self, human_tile: Tile) -> StateTransition {
+        StateTransition::Switch(Box::new(match self.saved_game.take() {
+            Some(saved_game) => PlayingState::from_saved_game(
+                saved_game,
+                self.opponent,
+                human_tile,
+                self.scoreboard.clone(),
+                self.settings.clone(),
+            ),
+            None => PlayingState::with_opponent(
+                self.opponent,
+                human_tile,
+                self.scoreboard.clone(),
+                self.settings.clone(),
+            ),
+        }))
+    }
+}
+
+impl MenuState for ChooseTileMenuState {
+    fn get_input_mode(&self) -> InputMode {
+        self.settings.borrow().get_menu_input_mode()
+    }
+
+    fn get_menu(&self) -> &Menu {
+        &self.menu
+    }
+
+    fn get_menu_theme(&self) -> MenuTheme {
+        self.settings.borrow().menu_theme.clone()
+    }
+
+    fn handle_selection(&mut self, entry: MenuEntryId) -> StateTransition {
+        if entry == self.o_entry {
+            return self.start_game(Tile::O);
+        } else if entry == self.x_entry {
+            return self.start_game(Tile::X);
+        }
+        StateTransition::Pop
+    }
+
+    fn render_header(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        renderer.write("Which tile would you like to play?\n\n")?;
+        Ok(())
+    }
+}
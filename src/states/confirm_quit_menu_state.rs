@@ -0,0 +1,54 @@
+use crate::{
+    input::InputMode,
+    menus::{Menu, MenuEntry, MenuEntryId, MenuState, MenuTheme},
+    rendering::{Error, Renderer},
+    settings::SharedSettings,
+    states::StateTransition,
+};
+
+/// The menu asking the user to confirm before leaving an in-progress game.
+pub struct ConfirmQuitMenuState {
+    menu: Menu,
+    quit_entry: MenuEntryId,
+    settings: SharedSettings,
+}
+
+impl ConfirmQuitMenuState {
+    pub fn new(settings: SharedSettings) -> Self {
+        let mut menu = Menu::new();
+        let quit_entry = menu.push(MenuEntry::new("Yes", '1'));
+        menu.push(MenuEntry::new("No", '2'));
+        ConfirmQuitMenuState {
+            menu,
+            quit_entry,
+            settings,
+        }
+    }
+}
+
+impl MenuState for ConfirmQuitMenuState {
+    fn get_input_mode(&self) -> InputMode {
+        self.settings.borrow().get_menu_input_mode()
+    }
+
+    fn get_menu(&self) -> &Menu {
+        &self.menu
+    }
+
+    fn get_menu_theme(&self) -> MenuTheme {
+        self.settings.borrow().menu_theme.clone()
+    }
+
+    fn handle_selection(&mut self, entry: MenuEntryId) -> StateTransition {
+        if entry == self.quit_entry {
+            return StateTransition::Quit;
+        }
+        // The game is still underneath, so going back to it is just a matter of removing this menu.
+        StateTransition::Pop
+    }
+
+    fn render_header(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        renderer.write("Really quit?\n\n")?;
+        Ok(())
+    }
+}
@@ -1,66 +1,511 @@
 use crate::{
     game::{
-        board::{Board, Tile},
+        board::{Board, GameOutcome, GameResult, PlayingPosition, Tile},
         players::Player,
     },
-    menus::{Menu, MenuEntry, MenuEntryId, MenuState},
+    input::InputMode,
+    menus::{Menu, MenuEntry, MenuEntryId, MenuState, MenuTheme},
     rendering::{Error, Renderer},
+    scores::{Match, SharedScoreboard},
+    settings::SharedSettings,
     states::{playing_state::PlayingState, StateTransition},
 };
+use std::{cell::Cell, fs, time::Duration};
 
-/// The menu displayed when the game ends. Will show the winning combo.
+/// The path of the file in which the transcript of a game is written.
+pub const TRANSCRIPT_FILE_PATH: &str = "tictactoe.transcript";
+
+/// The time between two steps of the reveal of the winning line.
+const REVEAL_FRAME_DELAY: Duration = Duration::from_millis(250);
+
+/// The time the result of a game stays on screen before the next game of a match starts.
+const NEXT_GAME_DELAY: Duration = Duration::from_secs(2);
+
+/// What is known of a game once it is over.
+pub struct FinishedGame {
+    /// The indices in `moves` of the moves of human players that gave up a better outcome (see `artificial_intelligence::is_blunder`).
+    pub blunders: Vec<usize>,
+    /// The board, as the last move left it.
+    pub board: Board,
+    /// The time the game lasted.
+    pub duration: Duration,
+    /// The tiles played during the game and their spots, in order.
+    pub moves: Vec<(Tile, PlayingPosition)>,
+    /// The tile that won the game, or `None` for a draw.
+    pub winner: Option<Tile>,
+}
+
+/// The menu displayed when the game ends. Will show the winning combo, revealing it tile by tile if the settings ask for it. During a match, the next game starts on its own until the match is over.
 pub struct EndGameMenuState {
+    /// The indices in `moves` of the moves of human players that gave up a better outcome.
+    blunders: Vec<usize>,
     board: Board,
+    change_opponent_entry: MenuEntryId,
+    /// The match the game was part of, including its result.
+    current_match: Match,
+    /// The time the game lasted.
+    duration: Duration,
+    /// Indicates whether the bell rings after the next rendering, to signal the end of the game.
+    is_bell_pending: Cell<bool>,
     menu: Menu,
+    /// The tiles played during the game and their spots, in order.
+    moves: Vec<(Tile, PlayingPosition)>,
     play_again_entry: MenuEntryId,
     players: Vec<Player>,
+    /// The number of winning tiles shown so far.
+    revealed_tiles: usize,
+    scoreboard: SharedScoreboard,
+    settings: SharedSettings,
+    /// A short message about the last performed operation.
+    status_message: Option<&'static str>,
+    transcript_entry: MenuEntryId,
     winner: Option<Tile>,
+    /// The tiles of all the winning lines, in the order in which they are revealed.
+    winning_tiles: Vec<PlayingPosition>,
 }
 
 impl EndGameMenuState {
-    pub fn new(board: Board, players: Vec<Player>, winner: Option<Tile>) -> Self {
+    pub fn new(
+        game: FinishedGame,
+        current_match: Match,
+        players: Vec<Player>,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let FinishedGame {
+            blunders,
+            mut board,
+            duration,
+            moves,
+            winner,
+        } = game;
         let mut menu = Menu::new();
-        let play_again_entry = menu.push(MenuEntry::new("Play again", 1));
-        menu.push(MenuEntry::new("Quit", 2));
+        let play_again_entry = menu.push(MenuEntry::new("Play again", '1'));
+        // A user who only watched computers play has no game to play again, but can choose to play against someone.
+        let is_spectator = players
+            .iter()
+            .all(|p| p.controller.is_automated() && !p.controller.is_remote());
+        menu.set_enabled(play_again_entry, !is_spectator);
+        let change_opponent_entry = menu.push(MenuEntry::new("Change opponent", '2'));
+        // The other player of a network game can't be changed.
+        if PlayingState::is_network_game(&players) {
+            menu.remove(change_opponent_entry);
+        }
+        menu.push(MenuEntry::new("Quit", '3'));
+        let transcript_entry = menu.push(MenuEntry::new(
+            format!("Save a transcript of the game to {}", TRANSCRIPT_FILE_PATH),
+            '4',
+        ));
+        let mut winning_tiles = Vec::new();
+        if let GameResult::Winner(_, solutions) = board.status() {
+            for spot in solutions.into_iter().flatten() {
+                if !winning_tiles.contains(&spot) {
+                    winning_tiles.push(spot);
+                }
+            }
+        }
+        let is_bell_pending = Cell::new(settings.borrow().bell);
+        let revealed_tiles = if settings.borrow().animate_win {
+            board.highlight_solutions(Vec::new());
+            0
+        } else {
+            winning_tiles.len()
+        };
         EndGameMenuState {
+            blunders,
             board,
+            change_opponent_entry,
+            current_match,
+            duration,
+            is_bell_pending,
             menu,
+            moves,
             play_again_entry,
             players,
+            revealed_tiles,
+            scoreboard,
+            settings,
+            status_message: None,
+            transcript_entry,
             winner,
+            winning_tiles,
+        }
+    }
+
+    /// Writes the game as text: a `moves` line followed by one line per move (its number, tile and spot label), a `result` line holding the tile of the winner or `draw`, then a `board` line followed by the rows of the final board. Tiles are written as in saved games.
+    fn to_transcript(&self) -> String {
+        let mut s = String::from("moves\n");
+        for (i, &(tile, position)) in self.moves.iter().enumerate() {
+            s.push_str(&format!(
+                "{} {} {}\n",
+                i + 1,
+                tile.to_save_char(),
+                self.board.get_position_label(position)
+            ));
+        }
+        s.push_str(&match self.winner {
+            Some(tile) => format!("result {}\n", tile.to_save_char()),
+            None => "result draw\n".to_string(),
+        });
+        s.push_str("board\n");
+        let encoded = self.board.encode().chars().collect::<Vec<char>>();
+        for row in encoded.chunks(self.board.size() as usize) {
+            s.extend(row.iter());
+            s.push('\n');
         }
+        s
+    }
+
+    /// Indicates whether the game was part of a match of several games.
+    fn is_in_match(&self) -> bool {
+        self.current_match.best_of > 1
+    }
+
+    /// Starts a game of the given match with the same players. The loser gets to start, otherwise the starting player follows the settings (see `PlayingState::pick_first_player`). In a network game, both sides must pick the same player without sharing their settings, so the player who did not start this game starts the next one after a draw.
+    fn next_game(&self, current_match: Match) -> StateTransition {
+        let players = self.players.clone();
+        let first = match self
+            .winner
+            .and_then(|tile| players.iter().position(|p| p.tile != tile))
+        {
+            Some(loser) => loser,
+            None if PlayingState::is_network_game(&players) => self
+                .moves
+                .first()
+                .and_then(|&(tile, _)| players.iter().position(|p| p.tile != tile))
+                .unwrap_or(0),
+            None => PlayingState::pick_first_player(&players, &self.settings.borrow()),
+        };
+        StateTransition::Switch(Box::new(PlayingState::with_players_in_match(
+            players,
+            first,
+            current_match,
+            self.scoreboard.clone(),
+            self.settings.clone(),
+        )))
+    }
+
+    /// Highlights the given number of winning tiles.
+    fn reveal(&mut self, count: usize) {
+        self.revealed_tiles = count.min(self.winning_tiles.len());
+        self.board
+            .highlight_solutions(vec![self.winning_tiles[..self.revealed_tiles].to_vec()]);
     }
 }
 
 impl MenuState for EndGameMenuState {
+    fn get_frame_delay(&self) -> Option<Duration> {
+        if self.revealed_tiles < self.winning_tiles.len() {
+            Some(REVEAL_FRAME_DELAY)
+        } else if !self.current_match.is_over() {
+            Some(NEXT_GAME_DELAY)
+        } else {
+            None
+        }
+    }
+
+    fn get_game_outcome(&self) -> Option<GameOutcome> {
+        Some(GameOutcome {
+            moves: self.moves.iter().map(|&(_, position)| position).collect(),
+            winner: self.winner,
+        })
+    }
+
+    fn get_input_mode(&self) -> InputMode {
+        self.settings.borrow().get_menu_input_mode()
+    }
+
     fn get_menu(&self) -> &Menu {
         &self.menu
     }
 
+    fn get_menu_theme(&self) -> MenuTheme {
+        self.settings.borrow().menu_theme.clone()
+    }
+
     fn handle_selection(&mut self, entry: MenuEntryId) -> StateTransition {
         if entry == self.play_again_entry {
-            return StateTransition::Switch(Box::new(PlayingState::with_players(
-                self.players.clone(),
-            )));
+            // Playing again in the middle of a match goes on with it, otherwise a new match starts.
+            let current_match = if self.current_match.is_over() {
+                Match::new(self.settings.borrow().match_length)
+            } else {
+                self.current_match
+            };
+            return self.next_game(current_match);
+        } else if entry == self.change_opponent_entry {
+            // The game replaced itself with this menu, so the choice of the opponent is right underneath. It shares the scoreboard, so the scores carry on with the next opponent.
+            return StateTransition::Pop;
+        } else if entry == self.transcript_entry {
+            self.status_message = Some(
+                match fs::write(TRANSCRIPT_FILE_PATH, self.to_transcript()) {
+                    Ok(_) => "Transcript saved.",
+                    Err(_) => "Could not save the transcript.",
+                },
+            );
+            return StateTransition::None;
         }
         StateTransition::Quit
     }
 
-    fn render_header(&self, renderer: &Renderer) -> Result<(), Error> {
-        self.board.render(renderer)?;
+    fn is_terminal(&self) -> bool {
+        true
+    }
+
+    fn render_header(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        let settings = self.settings.borrow();
+        let theme = &settings.tile_theme;
+        self.board.render(renderer, theme)?;
         renderer.write("\n\n")?;
         if let Some(ref tile) = self.winner {
-            tile.render(renderer)?;
+            tile.render(renderer, theme)?;
             renderer.write(" won!")?;
         } else {
             renderer.write("It's a draw!")?;
         }
-        renderer.write("\n\nScores:\n")?;
+        if self.is_in_match() {
+            renderer.write("\n\n")?;
+            if !self.current_match.is_over() {
+                renderer.write(&format!(
+                    "Game {} of a best of {}. The next game starts in a moment...",
+                    self.current_match.get_games_played(),
+                    self.current_match.best_of
+                ))?;
+            } else if let Some(tile) = self.current_match.get_winner() {
+                tile.render(renderer, theme)?;
+                renderer.write(" won the match!")?;
+            } else {
+                renderer.write("The match is a tie!")?;
+            }
+        }
+        renderer.write(&format!(
+            "\n\nMoves played: {}\nGame length: {}:{:02}\n",
+            self.moves.len(),
+            self.duration.as_secs() / 60,
+            self.duration.as_secs() % 60
+        ))?;
+        if let Some(&(tile, _)) = self.moves.first() {
+            renderer.write("First to move: ")?;
+            tile.render(renderer, theme)?;
+            renderer.write("\n")?;
+        }
+        // The blunders are only counted against the computer (see `PlayingState::counts_blunders`).
+        let counts_blunders = self.players.iter().any(|p| p.controller.is_automated());
+        for p in self
+            .players
+            .iter()
+            .filter(|p| counts_blunders && !p.controller.is_automated())
+        {
+            let count = self
+                .blunders
+                .iter()
+                .filter(|&&i| self.moves[i].0 == p.tile)
+                .count();
+            renderer.write("Blunders by ")?;
+            p.tile.render(renderer, theme)?;
+            renderer.write(&format!(": {}\n", count))?;
+        }
+        renderer.write("\nScores:\n")?;
+        let scoreboard = self.scoreboard.borrow();
         for p in self.players.iter() {
-            p.render(renderer)?;
+            p.render(renderer, theme, &scoreboard)?;
+            renderer.write("\n")?;
+        }
+        renderer.write(&format!("draws: {}\n", scoreboard.draws))?;
+        if let Some(message) = self.status_message {
+            renderer.write("\n")?;
+            renderer.write(message)?;
             renderer.write("\n")?;
         }
         renderer.write("\nWhat do you want to do now?\n\n")?;
         Ok(())
     }
+
+    fn skip_animation(&mut self) {
+        self.reveal(self.winning_tiles.len());
+    }
+
+    fn take_bell(&self) -> bool {
+        self.is_bell_pending.replace(false)
+    }
+
+    fn tick(&mut self) -> StateTransition {
+        if self.revealed_tiles < self.winning_tiles.len() {
+            self.reveal(self.revealed_tiles + 1);
+            return StateTransition::None;
+        }
+        self.next_game(self.current_match)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        game::players::{BasicComputerPlayerController, HumanPlayerController},
+        input::{InputEvent, Key, KeyBindings},
+        rendering::BufferRenderer,
+        states::{choose_opponent_menu_state::ChooseOpponentMenuState, State, StateManager},
+    };
+
+    /// Builds the menu shown once the given moves are played, as a single game between the given players.
+    fn end_game(players: Vec<Player>, moves: Vec<(Tile, PlayingPosition)>) -> EndGameMenuState {
+        let mut board = Board::new();
+        for &(tile, (x, y)) in moves.iter() {
+            let _ = board.set(x, y, tile);
+        }
+        let winner = board.status().winner().copied();
+        let game = FinishedGame {
+            blunders: Vec::new(),
+            board,
+            duration: Duration::from_secs(0),
+            moves,
+            winner,
+        };
+        EndGameMenuState::new(
+            game,
+            Match::new(1),
+            players,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        )
+    }
+
+    /// A human playing x against a computer playing o.
+    fn human_against_computer() -> Vec<Player> {
+        vec![
+            Player::new(
+                Box::new(HumanPlayerController::new(KeyBindings::default())),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(BasicComputerPlayerController::new(Tile::O)),
+                Tile::O,
+            ),
+        ]
+    }
+
+    #[test]
+    fn spectators_can_not_play_again() {
+        let computers = vec![
+            Player::new(
+                Box::new(BasicComputerPlayerController::new(Tile::X)),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(BasicComputerPlayerController::new(Tile::O)),
+                Tile::O,
+            ),
+        ];
+        let mut state = end_game(computers, Vec::new());
+        assert!(matches!(
+            state.handle_input(InputEvent::Line("1".to_string())),
+            StateTransition::None
+        ));
+        let mut state = end_game(human_against_computer(), Vec::new());
+        assert!(matches!(
+            state.handle_input(InputEvent::Line("1".to_string())),
+            StateTransition::Switch(_)
+        ));
+    }
+
+    #[test]
+    fn transcript_lists_the_moves_the_result_and_the_board() {
+        let moves = vec![
+            (Tile::X, (0, 0)),
+            (Tile::O, (0, 1)),
+            (Tile::X, (1, 0)),
+            (Tile::O, (1, 1)),
+            (Tile::X, (2, 0)),
+        ];
+        let state = end_game(human_against_computer(), moves);
+        assert_eq!(
+            state.to_transcript(),
+            "moves\n1 x a3\n2 o a2\n3 x b3\n4 o b2\n5 x c3\nresult x\nboard\nxxx\noo.\n...\n"
+        );
+    }
+
+    /// Chooses "Play again", and tells whose turn it is in the next game.
+    fn play_again(state: &mut EndGameMenuState) -> String {
+        match state.handle_input(InputEvent::Line("1".to_string())) {
+            StateTransition::Switch(game) => {
+                let renderer = BufferRenderer::new((80, 40));
+                assert!(game.render(&renderer).is_ok());
+                let contents = renderer.contents();
+                let start = contents.find("It's ").unwrap();
+                contents[start..start + 13].to_string()
+            }
+            _ => panic!("no game was started"),
+        }
+    }
+
+    #[test]
+    fn loser_starts_the_next_game() {
+        let x_wins = vec![
+            (Tile::X, (0, 0)),
+            (Tile::O, (0, 1)),
+            (Tile::X, (1, 0)),
+            (Tile::O, (1, 1)),
+            (Tile::X, (2, 0)),
+        ];
+        let mut state = end_game(human_against_computer(), x_wins);
+        assert_eq!(play_again(&mut state), "It's o's turn");
+        let o_wins = vec![
+            (Tile::X, (0, 0)),
+            (Tile::O, (0, 1)),
+            (Tile::X, (1, 0)),
+            (Tile::O, (1, 1)),
+            (Tile::X, (2, 2)),
+            (Tile::O, (2, 1)),
+        ];
+        let mut state = end_game(human_against_computer(), o_wins);
+        assert_eq!(play_again(&mut state), "It's x's turn");
+    }
+
+    /// Renders the current state of the given state manager, and returns the text drawn.
+    fn render_current(state_manager: &StateManager) -> String {
+        let renderer = BufferRenderer::new((80, 40));
+        assert!(state_manager.render(&renderer).is_ok());
+        let contents = renderer.contents();
+        contents.to_string()
+    }
+
+    /// Lets x win a game against a friend, started from the choice of the opponent with the given scoreboard, then chooses "Change opponent".
+    fn change_opponent_after_a_win(scoreboard: SharedScoreboard) -> StateManager {
+        let settings = SharedSettings::default();
+        settings.borrow_mut().first_mover = Some(Tile::X);
+        let mut state_manager =
+            StateManager::new(Box::new(ChooseOpponentMenuState::new(scoreboard, settings)));
+        let mut inputs = vec![
+            InputEvent::Line("1".to_string()),
+            InputEvent::Line("2".to_string()),
+        ];
+        for c in ['7', '4', '8', '5', '9'] {
+            inputs.push(InputEvent::Key(Key::Char(c)));
+        }
+        inputs.push(InputEvent::Line("2".to_string()));
+        for input in inputs {
+            assert!(!state_manager.handle_input(input).ok().unwrap());
+        }
+        state_manager
+    }
+
+    #[test]
+    fn changing_opponent_goes_back_to_their_choice() {
+        let state_manager = change_opponent_after_a_win(SharedScoreboard::default());
+        assert!(render_current(&state_manager).starts_with("Who would you like to play against?"));
+    }
+
+    #[test]
+    fn scores_carry_on_with_the_next_opponent() {
+        let scoreboard = SharedScoreboard::default();
+        let mut state_manager = change_opponent_after_a_win(scoreboard.clone());
+        // Play x against the easy computer.
+        for line in ["2", "2"] {
+            let input = InputEvent::Line(line.to_string());
+            assert!(!state_manager.handle_input(input).ok().unwrap());
+        }
+        assert!(render_current(&state_manager).contains("x: 1"));
+        assert_eq!(scoreboard.borrow().x, 1);
+    }
 }
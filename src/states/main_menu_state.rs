@@ -1,38 +1,108 @@
 use crate::{
-    menus::{Menu, MenuEntry, MenuEntryId, MenuState},
+    input::InputMode,
+    menus::{Menu, MenuEntry, MenuEntryId, MenuState, MenuTheme},
     rendering::{Error, Renderer},
-    states::{choose_opponent_menu_state::ChooseOpponentMenuState, StateTransition},
+    scores::{LifetimeScores, SharedScoreboard},
+    settings::SharedSettings,
+    states::{
+        choose_opponent_menu_state::ChooseOpponentMenuState,
+        playing_state::{SavedGame, SAVE_FILE_PATH},
+        settings_menu_state::SettingsMenuState,
+        StateTransition,
+    },
 };
+use std::path::Path;
 
 /// The main menu.
 pub struct MainMenuState {
+    continue_entry: MenuEntryId,
+    lifetime_scores: LifetimeScores,
     menu: Menu,
     play_entry: MenuEntryId,
+    settings: SharedSettings,
+    settings_entry: MenuEntryId,
+    /// Tells why the last choice failed, until another entry is chosen.
+    status_message: Option<String>,
 }
 
 impl MainMenuState {
-    pub fn new() -> Self {
+    pub fn new(settings: SharedSettings) -> Self {
         let mut menu = Menu::new();
-        let play_entry = menu.push(MenuEntry::new("Play", 1));
-        menu.push(MenuEntry::new("Quit", 2));
-        MainMenuState { menu, play_entry }
+        let play_entry = menu.push(MenuEntry::new("Play", '1'));
+        let continue_entry = menu.push(MenuEntry::new("Continue", '2'));
+        let settings_entry = menu.push(MenuEntry::new("Settings", '3'));
+        menu.push(MenuEntry::new("Quit", '4'));
+        let mut state = MainMenuState {
+            continue_entry,
+            lifetime_scores: LifetimeScores::default(),
+            menu,
+            play_entry,
+            settings,
+            settings_entry,
+            status_message: None,
+        };
+        state.refresh();
+        state
+    }
+
+    /// Reads again what the games played since may have changed on disk: the lifetime scores, and whether a game can be continued.
+    fn refresh(&mut self) {
+        self.lifetime_scores = LifetimeScores::load();
+        self.menu
+            .set_enabled(self.continue_entry, Path::new(SAVE_FILE_PATH).exists());
     }
 }
 
 impl MenuState for MainMenuState {
+    fn get_input_mode(&self) -> InputMode {
+        self.settings.borrow().get_menu_input_mode()
+    }
+
     fn get_menu(&self) -> &Menu {
         &self.menu
     }
 
+    fn get_menu_theme(&self) -> MenuTheme {
+        self.settings.borrow().menu_theme.clone()
+    }
+
     fn handle_selection(&mut self, entry: MenuEntryId) -> StateTransition {
+        self.status_message = None;
         if entry == self.play_entry {
-            return StateTransition::Push(Box::new(ChooseOpponentMenuState::new()));
+            return StateTransition::Push(Box::new(ChooseOpponentMenuState::new(
+                SharedScoreboard::default(),
+                self.settings.clone(),
+            )));
+        } else if entry == self.continue_entry {
+            return match SavedGame::load() {
+                Ok(saved_game) => StateTransition::Push(Box::new(
+                    ChooseOpponentMenuState::with_saved_game(saved_game, self.settings.clone()),
+                )),
+                Err(e) => {
+                    self.status_message = Some(format!("Could not continue the game: {}.", e));
+                    StateTransition::None
+                }
+            };
+        } else if entry == self.settings_entry {
+            return StateTransition::Push(Box::new(SettingsMenuState::new(self.settings.clone())));
         }
         StateTransition::Quit
     }
 
-    fn render_header(&self, renderer: &Renderer) -> Result<(), Error> {
+    fn render_header(&self, renderer: &dyn Renderer) -> Result<(), Error> {
         renderer.write("Tic Tac Toe\n\n")?;
+        renderer.write(&format!(
+            "Lifetime scores:\no: {}\nx: {}\n\n",
+            self.lifetime_scores.o, self.lifetime_scores.x
+        ))?;
+        if let Some(ref message) = self.status_message {
+            renderer.write(message)?;
+            renderer.write("\n\n")?;
+        }
         Ok(())
     }
+
+    fn resume(&mut self) {
+        self.refresh();
+    }
 }
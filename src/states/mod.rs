@@ -1,20 +1,60 @@
-mod choose_opponent_menu_state;
+pub mod choose_opponent_menu_state;
+mod choose_tile_menu_state;
+mod confirm_quit_menu_state;
 mod end_game_menu_state;
 pub mod main_menu_state;
-mod playing_state;
+pub mod playing_state;
+mod settings_menu_state;
+pub mod splash_state;
 
 use crate::{
+    game::board::GameOutcome,
     input::{InputEvent, InputMode},
     rendering::{Error, Renderer},
 };
+use std::{fmt, time::Duration};
 
 /// Represents a game state such as the main menu, the playing one and so on.
 pub trait State {
+    /// Gets the time to wait before the next frame of an animation, or `None` if this state is not animated right now.
+    fn get_frame_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Gets the outcome of the game this state reports, if any. Queried once, when a terminal state (see `is_terminal`) is entered.
+    fn get_game_outcome(&self) -> Option<GameOutcome> {
+        None
+    }
+
     fn get_input_mode(&self) -> InputMode;
 
     fn handle_input(&mut self, input: InputEvent) -> StateTransition;
 
-    fn render(&self, renderer: &Renderer) -> Result<(), Error>;
+    /// Indicates whether this state is reached once the game is over. Only such states report the outcome of the game.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+
+    /// Called when another state is pushed on top of this one, which stops being current until `resume` is called.
+    fn pause(&mut self) {}
+
+    fn render(&self, renderer: &dyn Renderer) -> Result<(), Error>;
+
+    /// Called when this state is current again, after the state on top of it was popped.
+    fn resume(&mut self) {}
+
+    /// Jumps to the last frame of the current animation.
+    fn skip_animation(&mut self) {}
+
+    /// Indicates whether the bell should ring, forgetting the request so that it rings only once. Checked after each rendering.
+    fn take_bell(&self) -> bool {
+        false
+    }
+
+    /// Advances the current animation by one frame. Returns the transition it leads to, if any.
+    fn tick(&mut self) -> StateTransition {
+        StateTransition::None
+    }
 }
 
 /// Indicates whether we should close the application.
@@ -22,10 +62,21 @@ pub type ShouldQuit = bool;
 
 /// Describes all kinds of error that can occure in the state manager.
 pub enum StateManagerError {
+    Input(std::io::Error),
     NoState,
     Rendering(Error),
 }
 
+impl fmt::Display for StateManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateManagerError::Input(e) => write!(f, "could not read the input: {}", e),
+            StateManagerError::NoState => write!(f, "there is no state to show"),
+            StateManagerError::Rendering(e) => e.fmt(f),
+        }
+    }
+}
+
 /// Represents all possible transitions between states.
 pub enum StateTransition {
     /// Nothing happens.
@@ -40,22 +91,68 @@ pub enum StateTransition {
     Switch(Box<dyn State>),
 }
 
+/// The kinds of transitions applied by the state manager, as recorded in its transition log.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TransitionKind {
+    Pop,
+    Push,
+    Quit,
+    Switch,
+}
+
+impl fmt::Display for TransitionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransitionKind::Pop => write!(f, "pop"),
+            TransitionKind::Push => write!(f, "push"),
+            TransitionKind::Quit => write!(f, "quit"),
+            TransitionKind::Switch => write!(f, "switch"),
+        }
+    }
+}
+
 /// A state machine.
 pub struct StateManager {
+    /// The outcome reported by the last entered state, until it is taken.
+    game_outcome: Option<GameOutcome>,
     states: Vec<Box<dyn State>>,
+    /// The transitions applied so far, in order, or `None` if they are not recorded (the default).
+    transition_log: Option<Vec<TransitionKind>>,
 }
 
 impl StateManager {
     /// Constructs a new state manager.
     pub fn new(first_state: Box<dyn State>) -> Self {
         StateManager {
+            game_outcome: None,
             states: vec![first_state],
+            transition_log: None,
         }
     }
 
+    /// Starts recording the transitions applied from now on. Used to debug the navigation between states.
+    pub fn enable_transition_log(&mut self) {
+        self.transition_log.get_or_insert_with(Vec::new);
+    }
+
+    /// Gets the time to wait before the next animation frame of the current state, if it is animated.
+    pub fn get_frame_delay(&self) -> Option<Duration> {
+        self.states.last().and_then(|s| s.get_frame_delay())
+    }
+
     /// Gets the state on top of the stack (the one currently shown to the user).
-    pub fn get_current_state(&self) -> Option<&Box<dyn State>> {
-        self.states.last()
+    pub fn get_current_state(&self) -> Option<&dyn State> {
+        self.states.last().map(|s| s.as_ref())
+    }
+
+    /// Gets the number of states on the stack: 1 when only the first state is open, one more for each state pushed on top of it.
+    pub fn get_depth(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Gets the transitions applied since the log was enabled, in order, or `None` if it is not.
+    pub fn get_transition_log(&self) -> Option<&[TransitionKind]> {
+        self.transition_log.as_deref()
     }
 
     /// Tells the current state to handle user input.
@@ -68,14 +165,37 @@ impl StateManager {
     }
 
     fn handle_transition(&mut self, transition: StateTransition) -> ShouldQuit {
+        if let Some(ref mut log) = self.transition_log {
+            match transition {
+                StateTransition::None => {}
+                StateTransition::Pop => log.push(TransitionKind::Pop),
+                StateTransition::Push(_) => log.push(TransitionKind::Push),
+                StateTransition::Quit => log.push(TransitionKind::Quit),
+                StateTransition::Switch(_) => log.push(TransitionKind::Switch),
+            }
+        }
         match transition {
             StateTransition::None => {}
             StateTransition::Pop => {
                 self.states.pop();
+                // Nothing is left to show once the last state is popped.
+                if self.is_empty() {
+                    return true;
+                }
+                if let Some(state) = self.states.last_mut() {
+                    state.resume();
+                }
+            }
+            StateTransition::Push(state) => {
+                self.record_game_outcome(state.as_ref());
+                if let Some(current) = self.states.last_mut() {
+                    current.pause();
+                }
+                self.states.push(state);
             }
-            StateTransition::Push(state) => self.states.push(state),
             StateTransition::Quit => return true,
             StateTransition::Switch(state) => {
+                self.record_game_outcome(state.as_ref());
                 self.states.pop();
                 self.states.push(state);
             }
@@ -83,13 +203,188 @@ impl StateManager {
         false
     }
 
+    /// Indicates whether the last state was popped, leaving nothing to show. The application quits then.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Pushes a state on top of the stack, as if the current state had asked for it.
+    pub fn push(&mut self, state: Box<dyn State>) {
+        self.handle_transition(StateTransition::Push(state));
+    }
+
+    /// Keeps the outcome of the game reported by the given state, about to be entered, if the game is over.
+    fn record_game_outcome(&mut self, state: &dyn State) {
+        if state.is_terminal() {
+            self.game_outcome = state.get_game_outcome();
+        }
+    }
+
     /// Renders the current state to the terminal.
-    pub fn render(&self, renderer: &Renderer) -> Result<(), StateManagerError> {
+    pub fn render(&self, renderer: &dyn Renderer) -> Result<(), StateManagerError> {
         if let Some(state) = self.states.last() {
-            return state
+            state
                 .render(renderer)
-                .map_err(|e| StateManagerError::Rendering(e));
+                .map_err(StateManagerError::Rendering)?;
+            if state.take_bell() {
+                renderer.bell().map_err(StateManagerError::Rendering)?;
+            }
+            return Ok(());
         }
         Err(StateManagerError::NoState)
     }
+
+    /// Takes the outcome of the game reported by the last entered state, if any.
+    pub fn take_game_outcome(&mut self) -> Option<GameOutcome> {
+        self.game_outcome.take()
+    }
+
+    /// Tells the current state to jump to the end of its animation.
+    pub fn skip_animation(&mut self) {
+        if let Some(state) = self.states.last_mut() {
+            state.skip_animation();
+        }
+    }
+
+    /// Advances the animation of the current state by one frame, applying the transition it leads to.
+    pub fn tick(&mut self) -> ShouldQuit {
+        if let Some(state) = self.states.last_mut() {
+            let transition = state.tick();
+            return self.handle_transition(transition);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A state applying the transition named by each line it is given.
+    struct StubState;
+
+    impl State for StubState {
+        fn get_input_mode(&self) -> InputMode {
+            InputMode::Line
+        }
+
+        fn handle_input(&mut self, input: InputEvent) -> StateTransition {
+            match input {
+                InputEvent::Line(ref line) if line == "pop" => StateTransition::Pop,
+                InputEvent::Line(ref line) if line == "push" => {
+                    StateTransition::Push(Box::new(StubState))
+                }
+                InputEvent::Line(ref line) if line == "quit" => StateTransition::Quit,
+                InputEvent::Line(ref line) if line == "switch" => {
+                    StateTransition::Switch(Box::new(StubState))
+                }
+                _ => StateTransition::None,
+            }
+        }
+
+        fn render(&self, _renderer: &dyn Renderer) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// Gives each line to the state manager in turn, returning whether the last one made it quit.
+    fn send_lines(state_manager: &mut StateManager, lines: &[&str]) -> ShouldQuit {
+        let mut should_quit = false;
+        for line in lines {
+            should_quit = state_manager
+                .handle_input(InputEvent::Line(line.to_string()))
+                .ok()
+                .unwrap();
+        }
+        should_quit
+    }
+
+    #[test]
+    fn depth_follows_pushes_and_pops() {
+        let mut state_manager = StateManager::new(Box::new(StubState));
+        assert_eq!(state_manager.get_depth(), 1);
+        send_lines(&mut state_manager, &["push", "push"]);
+        assert_eq!(state_manager.get_depth(), 3);
+        send_lines(&mut state_manager, &["switch", "pop"]);
+        assert_eq!(state_manager.get_depth(), 2);
+        state_manager.push(Box::new(StubState));
+        assert_eq!(state_manager.get_depth(), 3);
+        assert!(!state_manager.is_empty());
+    }
+
+    #[test]
+    fn popping_the_last_state_empties_the_stack_and_quits() {
+        let mut state_manager = StateManager::new(Box::new(StubState));
+        assert!(!send_lines(&mut state_manager, &["push", "pop"]));
+        assert!(send_lines(&mut state_manager, &["pop"]));
+        assert!(state_manager.is_empty());
+        assert!(state_manager.get_current_state().is_none());
+    }
+
+    #[test]
+    fn transition_log_is_off_by_default() {
+        let mut state_manager = StateManager::new(Box::new(StubState));
+        send_lines(&mut state_manager, &["push", "pop"]);
+        assert!(state_manager.get_transition_log().is_none());
+    }
+
+    #[test]
+    fn transition_log_records_transitions_in_order() {
+        let mut state_manager = StateManager::new(Box::new(StubState));
+        send_lines(&mut state_manager, &["push"]);
+        state_manager.enable_transition_log();
+        let should_quit = send_lines(
+            &mut state_manager,
+            &["push", "ignored", "switch", "pop", "quit"],
+        );
+        assert!(should_quit);
+        assert!(
+            state_manager.get_transition_log()
+                == Some(
+                    &[
+                        TransitionKind::Push,
+                        TransitionKind::Switch,
+                        TransitionKind::Pop,
+                        TransitionKind::Quit,
+                    ][..]
+                )
+        );
+    }
+
+    /// A state reporting a draw, reached once the game is over or not.
+    struct ReportingState(bool);
+
+    impl State for ReportingState {
+        fn get_game_outcome(&self) -> Option<GameOutcome> {
+            Some(GameOutcome {
+                moves: Vec::new(),
+                winner: None,
+            })
+        }
+
+        fn get_input_mode(&self) -> InputMode {
+            InputMode::Line
+        }
+
+        fn handle_input(&mut self, _input: InputEvent) -> StateTransition {
+            StateTransition::None
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.0
+        }
+
+        fn render(&self, _renderer: &dyn Renderer) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn only_terminal_states_report_the_outcome_of_the_game() {
+        let mut state_manager = StateManager::new(Box::new(StubState));
+        state_manager.push(Box::new(ReportingState(false)));
+        assert!(state_manager.take_game_outcome().is_none());
+        state_manager.push(Box::new(ReportingState(true)));
+        assert!(state_manager.take_game_outcome().is_some());
+    }
 }
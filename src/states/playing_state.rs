@@ -1,89 +1,322 @@
 use crate::{
+    error::GameError,
     game::{
-        board::{Board, GameResult, Tile},
-        players::{HumanPlayerController, Player, PlayerAction, PlayerController},
+        artificial_intelligence::{is_blunder, minimax},
+        board::{Board, GameResult, PlayingPosition, Tile},
+        players::{
+            HumanPlayerController, NetworkPlayerController, OpponentKind, Player, PlayerAction,
+            PlayerController,
+        },
     },
     input::{InputEvent, InputMode, Key},
-    rendering::{Error, Renderer},
-    states::{end_game_menu_state::EndGameMenuState, State, StateTransition},
+    network::Connection,
+    random,
+    rendering::{render_centered, Color, CursorPosition, Error, Renderer},
+    scores::{Match, SharedScoreboard},
+    settings::{Settings, SharedSettings},
+    states::{
+        confirm_quit_menu_state::ConfirmQuitMenuState,
+        end_game_menu_state::{EndGameMenuState, FinishedGame},
+        State, StateTransition,
+    },
+};
+use std::{
+    cell::Cell,
+    fs,
+    rc::Rc,
+    time::{Duration, Instant},
 };
-use rand::Rng;
+use textwrap::wrap;
+
+/// The path of the file in which an in-progress game is saved.
+pub const SAVE_FILE_PATH: &str = "tictactoe.save";
+
+/// The time waited before an automated player plays, so that its moves can be followed.
+const AUTOMATED_TURN_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest time between two refreshes of the clocks.
+const CLOCK_REFRESH_DELAY: Duration = Duration::from_secs(1);
+
+/// The widest the list of moves is drawn, in characters.
+const MOVES_WIDTH: usize = 40;
+
+/// An in-progress game, as saved on disk.
+#[derive(Clone)]
+pub struct SavedGame {
+    board: Board,
+    current_tile: Tile,
+}
+
+impl SavedGame {
+    /// Parses a saved game, as written by `to_save_string`.
+    pub fn from_save_string(content: &str) -> Result<SavedGame, GameError> {
+        let (first_line, rest) =
+            content.split_at(content.find('\n').ok_or_else(|| {
+                GameError::Parse("the saved game is missing its board".to_string())
+            })?);
+        let current_tile = first_line
+            .chars()
+            .next()
+            .and_then(Tile::from_save_char)
+            .filter(|t| *t != Tile::Empty)
+            .ok_or(GameError::Corrupt)?;
+        let board = Board::from_save_string(&rest[1..])?;
+        Ok(SavedGame {
+            board,
+            current_tile,
+        })
+    }
+
+    /// Reads the saved game from disk.
+    pub fn load() -> Result<SavedGame, GameError> {
+        SavedGame::from_save_string(&fs::read_to_string(SAVE_FILE_PATH)?)
+    }
+
+    /// Writes the game as text: the tile of the player whose turn it is on the first line, then the board.
+    pub fn to_save_string(&self) -> String {
+        let mut content = self.current_tile.to_save_char().to_string();
+        content.push('\n');
+        content.push_str(&self.board.to_save_string());
+        content
+    }
+}
 
 /// The playing state.
 pub struct PlayingState {
     board: Board,
+    /// The time left to each player, not counting the current turn. Unused if there is no time limit.
+    clocks: Vec<Duration>,
+    /// The match this game is part of.
+    current_match: Match,
     current_player: usize,
+    /// Indicates whether the game was last drawn with colors, which changes the width of the cells of the board (see `Board::get_cell_width`). Used to find the tile under a click.
+    has_colors: Cell<bool>,
+    /// The indices in `history` of the moves of human players that gave up a better outcome (see `is_blunder`).
+    blunders: Vec<usize>,
+    /// The tiles played so far and their spots, in order. Used to undo moves and shown below the board.
+    history: Vec<(Tile, PlayingPosition)>,
+    /// Indicates whether the bell rings after the next rendering, because a move was rejected.
+    is_bell_pending: Cell<bool>,
+    /// Indicates whether the best move of the human players is highlighted.
+    is_hint_shown: bool,
+    /// Indicates whether the last operation was an undo, in which case the move now on top of the history was already followed by a move of the next player.
+    is_last_move_undone: bool,
+    /// The position at which the game was last drawn, centered on the screen. Used to find the tile under a click.
+    origin: Cell<CursorPosition>,
     players: Vec<Player>,
+    scoreboard: SharedScoreboard,
+    settings: SharedSettings,
+    /// The moment the game started, or was resumed.
+    start: Instant,
+    /// A short message about the last performed operation, shown until the next input.
+    status_message: Option<&'static str>,
+    /// The time each player has to play the whole game, if limited.
+    time_limit: Option<Duration>,
+    /// The moment the current turn started.
+    turn_start: Instant,
 }
 
 impl PlayingState {
-    /// Constructs a playing state with a default human player and an opponent which kind is determined by its controller.
-    pub fn with_opponent(opponent_controller: Box<dyn PlayerController>) -> Self {
-        PlayingState::with_players(vec![
-            Player::new(Box::new(HumanPlayerController {}), Tile::O),
-            Player::new(opponent_controller, Tile::X),
-        ])
+    /// Constructs a playing state with a human player placing the given kind of tile, and an opponent of the given kind placing the other one.
+    pub fn with_opponent(
+        opponent: OpponentKind,
+        human_tile: Tile,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        PlayingState::with_players(
+            PlayingState::create_players(opponent, human_tile, &settings),
+            scoreboard,
+            settings,
+        )
+    }
+
+    fn create_players(
+        opponent: OpponentKind,
+        human_tile: Tile,
+        settings: &SharedSettings,
+    ) -> Vec<Player> {
+        let settings = &settings.borrow();
+        let opponent_tile = human_tile.opponent();
+        vec![
+            Player::new(
+                Box::new(HumanPlayerController::with_settings(settings)),
+                human_tile,
+            ),
+            Player::new(
+                opponent.create_controller(opponent_tile, settings),
+                opponent_tile,
+            ),
+        ]
+    }
+
+    /// Constructs a playing state against a player on another computer, over the given connection. The host plays o and starts, the other side plays x.
+    pub fn with_remote_player(
+        connection: Rc<Connection>,
+        is_host: bool,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let local: Box<dyn PlayerController> =
+            Box::new(HumanPlayerController::with_settings(&settings.borrow()));
+        let remote: Box<dyn PlayerController> = Box::new(NetworkPlayerController::new(connection));
+        // Both sides list the o player first, so that they pick the same player to start the next games.
+        let players = if is_host {
+            vec![Player::new(local, Tile::O), Player::new(remote, Tile::X)]
+        } else {
+            vec![Player::new(remote, Tile::O), Player::new(local, Tile::X)]
+        };
+        PlayingState::with_players_starting(players, 0, scoreboard, settings)
+    }
+
+    /// Constructs a playing state resuming a saved game, with the same players as `with_opponent`.
+    pub fn from_saved_game(
+        saved_game: SavedGame,
+        opponent: OpponentKind,
+        human_tile: Tile,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let players = PlayingState::create_players(opponent, human_tile, &settings);
+        let current_player = players
+            .iter()
+            .position(|p| p.tile == saved_game.current_tile)
+            .unwrap_or(0);
+        PlayingState::with_board(
+            saved_game.board,
+            current_player,
+            players,
+            scoreboard,
+            settings,
+        )
     }
 
-    /// Constructs a playing state from a list of two existing players. Used to restart the game.
-    pub fn with_players(players: Vec<Player>) -> Self {
-        let mut rng = rand::thread_rng();
+    /// Constructs a playing state from a list of two existing players, the settings telling which one starts. Used to restart the game.
+    pub fn with_players(
+        players: Vec<Player>,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let first = PlayingState::pick_first_player(&players, &settings.borrow());
+        PlayingState::with_players_starting(players, first, scoreboard, settings)
+    }
+
+    /// Constructs a playing state from a list of two existing players, the one at index `first` starting. The game opens a new match, as long as the settings ask for.
+    pub fn with_players_starting(
+        players: Vec<Player>,
+        first: usize,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let current_match = Match::new(settings.borrow().match_length);
+        PlayingState::with_players_in_match(players, first, current_match, scoreboard, settings)
+    }
+
+    /// Constructs the next game of the given match, the player at index `first` starting.
+    pub fn with_players_in_match(
+        players: Vec<Player>,
+        first: usize,
+        current_match: Match,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let mut state = PlayingState::with_board(
+            Board::new(),
+            first % players.len(),
+            players,
+            scoreboard,
+            settings,
+        );
+        state.current_match = current_match;
+        state
+    }
+
+    fn with_board(
+        board: Board,
+        current_player: usize,
+        players: Vec<Player>,
+        scoreboard: SharedScoreboard,
+        settings: SharedSettings,
+    ) -> Self {
+        let (match_length, time_limit) = {
+            let settings = settings.borrow();
+            (settings.match_length, settings.time_limit)
+        };
         let mut state = PlayingState {
-            board: Board::new(),
-            current_player: rng.gen_range(0, 2),
+            blunders: Vec::new(),
+            board,
+            clocks: vec![time_limit.unwrap_or_default(); players.len()],
+            current_match: Match::new(match_length),
+            current_player,
+            has_colors: Cell::new(true),
+            history: Vec::new(),
+            is_bell_pending: Cell::new(false),
+            is_hint_shown: false,
+            is_last_move_undone: false,
+            origin: Cell::new((0, 0)),
             players,
+            scoreboard,
+            settings,
+            start: Instant::now(),
+            status_message: None,
+            time_limit,
+            turn_start: Instant::now(),
         };
-        state.handle_action(
-            state.players[state.current_player]
-                .controller
-                .start_turn(&state.board),
-        );
+        state.start_turn();
         state
     }
 
+    /// Indicates whether the blunders of the human players are counted: only when the computer (or a remote player) takes part, since a game between humans sharing the keyboard is not a lesson.
+    fn counts_blunders(&self) -> bool {
+        self.players.iter().any(|p| p.controller.is_automated())
+    }
+
+    /// Indicates whether a player of this game plays on another computer (see `PlayerController::is_remote`).
+    pub fn is_network_game(players: &[Player]) -> bool {
+        players.iter().any(|p| p.controller.is_remote())
+    }
+
     fn handle_action(&mut self, action: PlayerAction) -> StateTransition {
         match action {
             PlayerAction::Move(pos) => self.board.playing_position = pos,
             PlayerAction::Play((x, y)) => {
+                let player = self.get_current_player();
+                let is_blunder = self.counts_blunders()
+                    && !player.controller.is_automated()
+                    && self.get_available_moves().contains(&(x, y))
+                    && is_blunder(&self.board, player.tile, (x, y));
+                let result = match self.board.set(x, y, self.get_current_player().tile) {
+                    Ok(result) => result,
+                    // The move was rejected, so the turn does not pass.
+                    Err(_) => {
+                        self.ring_bell();
+                        return StateTransition::None;
+                    }
+                };
                 self.board.playing_position = (x, y);
-                match self
-                    .board
-                    .set(x, y, self.players[self.current_player].tile.clone())
-                {
+                for (i, player) in self.players.iter().enumerate() {
+                    if i != self.current_player {
+                        player.controller.notify_move((x, y));
+                    }
+                }
+                if is_blunder {
+                    self.blunders.push(self.history.len());
+                }
+                self.history.push((self.get_current_player().tile, (x, y)));
+                self.is_last_move_undone = false;
+                self.stop_clock();
+                match result {
                     GameResult::Draw => {
-                        return StateTransition::Switch(Box::new(EndGameMenuState::new(
-                            self.board.clone(),
-                            self.players.clone(),
-                            None,
-                        )))
+                        self.board.highlight_solutions(Vec::new());
+                        return self.end_game(None);
                     }
-                    GameResult::Winner(tile, solution) => {
-                        self.board.highlight_solution(solution);
-                        return StateTransition::Switch(Box::new(EndGameMenuState::new(
-                            self.board.clone(),
-                            self.players
-                                .iter()
-                                .map(|p| {
-                                    if p.tile == tile {
-                                        return Player {
-                                            controller: p.controller.clone(),
-                                            score: p.score + 1,
-                                            tile: tile.clone(),
-                                        };
-                                    }
-                                    p.clone()
-                                })
-                                .collect::<Vec<Player>>(),
-                            Some(tile),
-                        )));
+                    GameResult::Winner(tile, solutions) => {
+                        self.board.highlight_solutions(solutions);
+                        return self.end_game(Some(tile));
                     }
                     _ => {
                         self.current_player = (self.current_player + 1) % 2;
-                        return self.handle_action(
-                            self.players[self.current_player]
-                                .controller
-                                .start_turn(&self.board),
-                        );
+                        return self.start_turn();
                     }
                 }
             }
@@ -91,41 +324,690 @@ impl PlayingState {
         }
         StateTransition::None
     }
+
+    /// Takes the time spent since the start of the turn from the clock of the current player, and starts counting again.
+    fn stop_clock(&mut self) {
+        let remaining = self.get_remaining_time().unwrap_or_default();
+        self.clocks[self.current_player] = remaining;
+        self.turn_start = Instant::now();
+    }
+
+    /// Starts the turn of the current player. The turn of an automated player is only played once its delay is over, see `tick`.
+    fn start_turn(&mut self) -> StateTransition {
+        self.refresh_hint();
+        let controller = &self.get_current_player().controller;
+        if controller.is_automated() {
+            return StateTransition::None;
+        }
+        let action = controller.start_turn(&self.board);
+        self.handle_action(action)
+    }
+
+    /// Moves on to the end of the game, counting a win for the given tile, or a draw, both on the scoreboard and in the match.
+    fn end_game(&self, winner: Option<Tile>) -> StateTransition {
+        self.scoreboard.borrow_mut().record(winner);
+        let mut current_match = self.current_match;
+        current_match.results.record(winner);
+        let game = FinishedGame {
+            board: self.board.clone(),
+            duration: self.start.elapsed(),
+            blunders: self.blunders.clone(),
+            moves: self.history.clone(),
+            winner,
+        };
+        StateTransition::Switch(Box::new(EndGameMenuState::new(
+            game,
+            current_match,
+            self.players.clone(),
+            self.scoreboard.clone(),
+            self.settings.clone(),
+        )))
+    }
+
+    /// Gets the spots the current player can play.
+    pub fn get_available_moves(&self) -> Vec<PlayingPosition> {
+        self.board.get_available_spots()
+    }
+
+    /// Gets the player whose turn it is.
+    pub fn get_current_player(&self) -> &Player {
+        &self.players[self.current_player]
+    }
+
+    /// Gets the time left to the current player, if the time is limited.
+    fn get_remaining_time(&self) -> Option<Duration> {
+        self.time_limit?;
+        Some(self.clocks[self.current_player].saturating_sub(self.turn_start.elapsed()))
+    }
+
+    /// Gets the index of the player who starts a game: the one playing the tile of `first_mover` if the settings fix it, or a random one.
+    pub fn pick_first_player(players: &[Player], settings: &Settings) -> usize {
+        settings
+            .first_mover
+            .and_then(|tile| players.iter().position(|p| p.tile == tile))
+            .unwrap_or_else(|| random::gen_range(0, players.len()))
+    }
+
+    /// Finds the spot of the board drawn on the given cell of the screen, if any.
+    ///
+    /// The grid is drawn from the top left corner of the game, which is centered on the screen (see `origin`), past the labels if any (see `Board::get_grid_offset`). Each tile takes `cell_width` columns (see `Board::get_cell_width`) followed by a `|` separator, and one row followed by a `-` separator row. Tile `(x, y)` thus starts at column `x * (cell_width + 1)` and row `y * 2` from that corner, and clicks on the separators are ignored.
+    fn position_at(&self, (column, row): CursorPosition) -> Option<PlayingPosition> {
+        let theme = &self.settings.borrow().tile_theme;
+        let origin = self.origin.get();
+        let grid_offset = self.board.get_grid_offset(theme);
+        let (left, top) = (origin.0 + grid_offset.0, origin.1 + grid_offset.1);
+        if column < left || row < top {
+            return None;
+        }
+        let (column, row) = (column - left, row - top);
+        let cell_width = Board::get_cell_width(theme, self.has_colors.get()) as u16;
+        let (x, y) = (column / (cell_width + 1), row / 2);
+        if column % (cell_width + 1) == cell_width || row % 2 == 1 {
+            return None;
+        }
+        if x >= self.board.size() as u16 || y >= self.board.size() as u16 {
+            return None;
+        }
+        Some((x as u8, y as u8))
+    }
+
+    /// Asks for the bell to ring after the next rendering, if the settings allow it.
+    fn ring_bell(&self) {
+        if self.settings.borrow().bell {
+            self.is_bell_pending.set(true);
+        }
+    }
+
+    /// Highlights the best move of the current player if hints are shown and it is a human, or clears the highlight otherwise.
+    fn refresh_hint(&mut self) {
+        let player = self.get_current_player();
+        let hint = if self.is_hint_shown && !player.controller.is_automated() {
+            let mut temp_board = self.board.clone();
+            vec![vec![minimax(&mut temp_board, player.tile).pos]]
+        } else {
+            Vec::new()
+        };
+        self.board.highlight_solutions(hint);
+    }
+
+    /// Scraps the board and starts the game over with the same players, the same one starting. The game is not counted, neither on the scoreboard nor in the match. Only allowed on the turn of a human player, so that a computer thinking does not get interrupted.
+    fn restart(&mut self) -> StateTransition {
+        if self.get_current_player().controller.is_automated() {
+            self.status_message = Some("Wait for your turn to restart the game.");
+            return StateTransition::None;
+        }
+        let first = self
+            .history
+            .first()
+            .and_then(|&(tile, _)| self.players.iter().position(|p| p.tile == tile))
+            .unwrap_or(self.current_player);
+        StateTransition::Switch(Box::new(PlayingState::with_players_in_match(
+            self.players.clone(),
+            first,
+            self.current_match,
+            self.scoreboard.clone(),
+            self.settings.clone(),
+        )))
+    }
+
+    /// Draws the board, the current turn, the scores and the moves played so far, leaving the cursor on the selected tile.
+    fn render_game(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        let settings = self.settings.borrow();
+        let theme = &settings.tile_theme;
+        renderer.clear()?;
+        self.board.render(renderer, theme)?;
+        renderer.write("\n\nIt's ")?;
+        let current_tile = self.get_current_player().tile;
+        renderer.set_foreground_color(theme.get_color(current_tile))?;
+        current_tile.render(renderer, theme)?;
+        renderer.set_foreground_color(Color::Reset)?;
+        renderer.write("'s turn.\n\nScores:\n")?;
+        for (i, p) in self.players.iter().enumerate() {
+            p.render(renderer, theme, &self.scoreboard.borrow())?;
+            if self.time_limit.is_some() {
+                let remaining = if i == self.current_player {
+                    self.get_remaining_time().unwrap_or_default()
+                } else {
+                    self.clocks[i]
+                };
+                renderer.write(&format!(
+                    " - {}:{:02} left",
+                    remaining.as_secs() / 60,
+                    remaining.as_secs() % 60
+                ))?;
+            }
+            renderer.write("\n")?;
+        }
+        renderer.write(&format!("draws: {}\n", self.scoreboard.borrow().draws))?;
+        if !self.history.is_empty() {
+            let moves = self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, &(tile, position))| {
+                    format!(
+                        "{}. {} {}",
+                        i + 1,
+                        theme.get_symbol(tile),
+                        self.board.get_position_label(position)
+                    )
+                })
+                .collect::<Vec<String>>();
+            // Wrap the moves so that the board is not scrolled away in a small terminal. The lines are padded to the full width, so that the centered game does not move sideways as moves are added.
+            renderer.write("\n")?;
+            let width = (renderer.get_screen_size().0 as usize).clamp(1, MOVES_WIDTH);
+            for line in wrap(&format!("Moves: {}", moves.join("  ")), width) {
+                renderer.write(&format!("{:1$}\n", line, width))?;
+            }
+        }
+        if let Some(message) = self.status_message {
+            renderer.write("\n")?;
+            renderer.write(message)?;
+            renderer.write("\n")?;
+        }
+        // Leave the cursor on the symbol of the selected tile, past the room left for the brackets if any.
+        self.has_colors.set(renderer.has_colors());
+        let cell_width = Board::get_cell_width(theme, renderer.has_colors()) as u16;
+        let padding = (cell_width - theme.get_cell_width() as u16) / 2;
+        let (left, top) = self.board.get_grid_offset(theme);
+        renderer.set_cursor_position((
+            left + (self.board.playing_position.0 as u16) * (cell_width + 1) + padding,
+            top + (self.board.playing_position.1 as u16) * 2,
+        ))
+    }
+
+    /// Writes the current game to disk so that it can be resumed later.
+    fn save(&self) -> Result<(), GameError> {
+        let saved_game = SavedGame {
+            board: self.board.clone(),
+            current_tile: self.get_current_player().tile,
+        };
+        fs::write(SAVE_FILE_PATH, saved_game.to_save_string()).map_err(GameError::from)
+    }
+
+    /// Reverts the last move, as well as the moves of any computer opponent that played since, so that a human gets to play again. Does nothing if there is no such move to go back to.
+    ///
+    /// If the settings only allow to undo the last move, a move can only be taken back before the next player moves: undoing a move that was followed by another one is refused.
+    fn undo(&mut self) {
+        let mut count = 1;
+        while count <= self.history.len()
+            && self.players[(self.current_player + count) % 2]
+                .controller
+                .is_automated()
+        {
+            count += 1;
+        }
+        if count > self.history.len() {
+            return;
+        }
+        if self.settings.borrow().strict_undo && (count > 1 || self.is_last_move_undone) {
+            self.status_message = Some("Only the last move can be undone.");
+            return;
+        }
+        // A computer may be thinking about the position being taken back.
+        self.get_current_player().controller.cancel_turn();
+        for _ in 0..count {
+            if let Some((_, (x, y))) = self.history.pop() {
+                self.board.reset(x, y);
+                self.board.playing_position = (x, y);
+            }
+        }
+        let length = self.history.len();
+        self.blunders.retain(|&i| i < length);
+        self.stop_clock();
+        self.current_player = (self.current_player + count) % 2;
+        self.is_last_move_undone = true;
+    }
 }
 
 impl State for PlayingState {
+    fn get_frame_delay(&self) -> Option<Duration> {
+        if self.get_current_player().controller.is_automated() {
+            return Some(AUTOMATED_TURN_DELAY);
+        }
+        // Keep the clock of a human player ticking while waiting for its move.
+        self.get_remaining_time()
+            .map(|remaining| remaining.min(CLOCK_REFRESH_DELAY))
+    }
+
     fn get_input_mode(&self) -> InputMode {
-        InputMode::Key
+        if self.settings.borrow().mouse {
+            InputMode::KeyAndMouse
+        } else {
+            InputMode::Key
+        }
     }
 
     fn handle_input(&mut self, input: InputEvent) -> StateTransition {
+        self.status_message = None;
         if let InputEvent::Key(k) = input {
-            if k == Key::Escape {
-                return StateTransition::Quit;
+            let key_bindings = self.settings.borrow().key_bindings.clone();
+            // Only the moves are shared with a remote player, so leaving the game for another one, undoing, restarting and hints (which draw lots among the best moves) are left out.
+            let is_local_only = key_bindings.back.contains(&k)
+                || key_bindings.undo.contains(&k)
+                || k == Key::Char('?')
+                || k == Key::Char('r');
+            if is_local_only && PlayingState::is_network_game(&self.players) {
+                self.status_message = Some("Not available in a network game.");
+            } else if key_bindings.back.contains(&k) {
+                return StateTransition::Pop;
+            } else if key_bindings.quit.contains(&k) {
+                if !self.settings.borrow().confirm_quit {
+                    return StateTransition::Quit;
+                }
+                return StateTransition::Push(Box::new(ConfirmQuitMenuState::new(
+                    self.settings.clone(),
+                )));
+            } else if key_bindings.undo.contains(&k) {
+                self.undo();
+                self.refresh_hint();
+            } else if k == Key::Char('?') {
+                self.is_hint_shown = !self.is_hint_shown;
+                self.refresh_hint();
+            } else if k == Key::Char('r') {
+                return self.restart();
+            } else if k == Key::Char('s') {
+                self.status_message = Some(match self.save() {
+                    Ok(_) => "Game saved.",
+                    Err(_) => "Could not save the game.",
+                });
             } else {
-                return self.handle_action(
-                    self.players[self.current_player]
-                        .controller
-                        .handle_key_press(&self.board, k),
-                );
+                // A key meant to play a tile that does nothing points at an occupied spot.
+                let is_play_key = key_bindings.confirm.contains(&k)
+                    || (self.board.size() == 3 && matches!(k, Key::Char('1'..='9')));
+                let action = self
+                    .get_current_player()
+                    .controller
+                    .handle_key_press(&self.board, k);
+                if is_play_key && matches!(action, PlayerAction::None) {
+                    self.ring_bell();
+                }
+                return self.handle_action(action);
+            }
+        } else if let InputEvent::Mouse(cell) = input {
+            // Clicking a tile plays it, but only for a human player.
+            if let Some(position) = self.position_at(cell) {
+                if !self.get_current_player().controller.is_automated() {
+                    return self.handle_action(PlayerAction::Play(position));
+                }
             }
         }
         StateTransition::None
     }
 
-    fn render(&self, renderer: &Renderer) -> Result<(), Error> {
-        renderer.clear()?;
-        self.board.render(renderer)?;
-        renderer.write("\n\nIt's ")?;
-        self.players[self.current_player].tile.render(renderer)?;
-        renderer.write("'s turn.\n\nScores:\n")?;
-        for p in self.players.iter() {
-            p.render(renderer)?;
-            renderer.write("\n")?;
+    fn pause(&mut self) {
+        // The clock does not run while the game is out of sight, such as when asked to confirm quitting.
+        self.stop_clock();
+    }
+
+    fn render(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        self.origin
+            .set(render_centered(renderer, |r| self.render_game(r))?);
+        Ok(())
+    }
+
+    fn resume(&mut self) {
+        self.turn_start = Instant::now();
+    }
+
+    fn take_bell(&self) -> bool {
+        self.is_bell_pending.replace(false)
+    }
+
+    fn tick(&mut self) -> StateTransition {
+        // A player whose time is up loses the game.
+        if self.get_remaining_time() == Some(Duration::from_secs(0)) {
+            let winner = self.get_current_player().tile.opponent();
+            return self.end_game(Some(winner));
         }
-        renderer.set_cursor_position((
-            (self.board.playing_position.0 as u16) * 2,
-            (self.board.playing_position.1 as u16) * 2,
-        ))
+        if self.players.iter().any(|p| p.controller.has_left()) {
+            self.status_message = Some("The other player left the game.");
+            return StateTransition::None;
+        }
+        // Only automated players make the game move on by themselves.
+        let controller = &self.get_current_player().controller;
+        if !controller.is_automated() {
+            return StateTransition::None;
+        }
+        let action = controller.start_turn(&self.board);
+        self.handle_action(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::players::{
+            BasicComputerPlayerController, HumanPlayerController, ScriptedPlayerController,
+        },
+        input::KeyBindings,
+        states::{
+            choose_opponent_menu_state::ChooseOpponentMenuState, main_menu_state::MainMenuState,
+            StateManager,
+        },
+    };
+
+    /// Plays a game between two scripted players, x starting, and returns its winner.
+    fn play_scripted_game(
+        x_moves: Vec<PlayingPosition>,
+        o_moves: Vec<PlayingPosition>,
+    ) -> Option<Tile> {
+        let players = vec![
+            Player::new(Box::new(ScriptedPlayerController::new(x_moves)), Tile::X),
+            Player::new(Box::new(ScriptedPlayerController::new(o_moves)), Tile::O),
+        ];
+        let game = PlayingState::with_players_starting(
+            players,
+            0,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        );
+        let mut state_manager = StateManager::new(Box::new(game));
+        for _ in 0..9 {
+            state_manager.tick();
+            if let Some(outcome) = state_manager.take_game_outcome() {
+                return outcome.winner;
+            }
+        }
+        panic!("the game did not end");
+    }
+
+    #[test]
+    fn only_the_end_game_state_is_terminal() {
+        let players = vec![
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(0, 0), (1, 1), (2, 2)])),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(1, 0), (2, 0)])),
+                Tile::O,
+            ),
+        ];
+        let game = PlayingState::with_players_starting(
+            players,
+            0,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        );
+        let mut state_manager = StateManager::new(Box::new(game));
+        assert!(!state_manager.get_current_state().unwrap().is_terminal());
+        while state_manager.take_game_outcome().is_none() {
+            state_manager.tick();
+        }
+        assert!(state_manager.get_current_state().unwrap().is_terminal());
+    }
+
+    #[test]
+    fn scripted_game_ends_with_the_expected_winner() {
+        let winner = play_scripted_game(vec![(0, 0), (1, 1), (2, 2)], vec![(1, 0), (2, 0)]);
+        assert!(winner == Some(Tile::X));
+    }
+
+    #[test]
+    fn accessors_describe_a_game_in_progress() {
+        let mut board = Board::new();
+        let _ = board.set(0, 0, Tile::X);
+        let _ = board.set(1, 1, Tile::O);
+        let saved_game = SavedGame {
+            board,
+            current_tile: Tile::X,
+        };
+        let game = PlayingState::from_saved_game(
+            saved_game,
+            OpponentKind::Friend,
+            Tile::O,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        );
+        assert!(game.get_current_player().tile == Tile::X);
+        let moves = game.get_available_moves();
+        assert_eq!(moves.len(), 7);
+        assert!(!moves.contains(&(0, 0)) && !moves.contains(&(1, 1)));
+    }
+
+    #[test]
+    fn scripted_player_skips_an_occupied_spot() {
+        // o tries to play the center, which x already took, so the game waits for o until the script is fixed.
+        let players = vec![
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(1, 1)])),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(ScriptedPlayerController::new(vec![(1, 1)])),
+                Tile::O,
+            ),
+        ];
+        let mut game = PlayingState::with_players_starting(
+            players,
+            0,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        );
+        game.tick();
+        game.tick();
+        assert_eq!(game.history.len(), 1);
+        assert!(game.players[game.current_player].tile == Tile::O);
+    }
+
+    #[test]
+    fn saved_game_round_trip() {
+        let (board, _) = Board::new().with_move((1, 1), Tile::X).ok().unwrap();
+        let (board, _) = board.with_move((0, 2), Tile::O).ok().unwrap();
+        let saved_game = SavedGame {
+            board,
+            current_tile: Tile::X,
+        };
+        let content = saved_game.to_save_string();
+        let loaded = SavedGame::from_save_string(&content).ok().unwrap();
+        assert!(loaded.current_tile == Tile::X);
+        assert_eq!(
+            loaded.board.to_save_string(),
+            saved_game.board.to_save_string()
+        );
+        assert_eq!(loaded.to_save_string(), content);
+    }
+
+    #[test]
+    fn corrupt_saved_game_is_a_parse_error() {
+        let content = "x\n3\nx-?\n---\n---\n1\n";
+        match SavedGame::from_save_string(content) {
+            Err(e @ GameError::Parse(_)) => {
+                assert_eq!(e.to_string(), "could not parse the file: a tile is unknown")
+            }
+            _ => panic!("the corrupt saved game was not refused as unparsable"),
+        }
+        assert!(matches!(
+            SavedGame::from_save_string("x"),
+            Err(GameError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn saved_game_without_a_current_tile_is_refused() {
+        let content = format!("-\n{}", Board::new().to_save_string());
+        assert!(SavedGame::from_save_string(&content).is_err());
+    }
+
+    /// Starts a game between two humans, x starting, with undos restricted to the last move or not.
+    fn human_game(strict_undo: bool) -> PlayingState {
+        let settings = SharedSettings::default();
+        settings.borrow_mut().strict_undo = strict_undo;
+        human_game_with_settings(settings)
+    }
+
+    /// Starts a game between two humans, x starting, with the given settings.
+    fn human_game_with_settings(settings: SharedSettings) -> PlayingState {
+        let players = vec![
+            Player::new(
+                Box::new(HumanPlayerController::new(KeyBindings::default())),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(HumanPlayerController::new(KeyBindings::default())),
+                Tile::O,
+            ),
+        ];
+        PlayingState::with_players_starting(players, 0, SharedScoreboard::default(), settings)
+    }
+
+    /// Presses each key in turn.
+    fn press_keys(game: &mut PlayingState, keys: &[Key]) {
+        for key in keys {
+            game.handle_input(InputEvent::Key(key.clone()));
+        }
+    }
+
+    #[test]
+    fn strict_undo_only_takes_back_the_last_move() {
+        let mut game = human_game(true);
+        // x takes back its move before o plays.
+        press_keys(&mut game, &[Key::Char('7'), Key::Backspace]);
+        assert!(game.history.is_empty());
+        assert!(game.get_current_player().tile == Tile::X);
+        // o takes back its move too, but the move of x was followed by it, so it stays.
+        press_keys(&mut game, &[Key::Char('7'), Key::Char('5'), Key::Backspace]);
+        assert_eq!(game.history.len(), 1);
+        press_keys(&mut game, &[Key::Backspace]);
+        assert_eq!(game.history.len(), 1);
+        assert!(game.status_message.is_some());
+    }
+
+    #[test]
+    fn undo_takes_back_any_move_unless_strict() {
+        let mut game = human_game(false);
+        press_keys(
+            &mut game,
+            &[
+                Key::Char('7'),
+                Key::Char('5'),
+                Key::Backspace,
+                Key::Backspace,
+            ],
+        );
+        assert!(game.history.is_empty());
+        assert!(game.get_current_player().tile == Tile::X);
+    }
+
+    #[test]
+    fn running_out_of_time_loses_the_game() {
+        let settings = SharedSettings::default();
+        settings.borrow_mut().time_limit = Some(Duration::from_secs(0));
+        let mut game = human_game_with_settings(settings);
+        // x plays in no time, and o's clock is the one found at zero.
+        press_keys(&mut game, &[Key::Char('7')]);
+        match game.tick() {
+            StateTransition::Switch(state) => {
+                assert!(state.is_terminal());
+                let outcome = state.get_game_outcome().unwrap();
+                assert!(outcome.winner == Some(Tile::X));
+                assert!(outcome.moves == vec![(0, 0)]);
+            }
+            _ => panic!("the game should have ended"),
+        }
+    }
+
+    #[test]
+    fn restarting_empties_the_board_but_keeps_the_scores() {
+        let mut game = human_game(false);
+        let scoreboard = game.scoreboard.clone();
+        scoreboard.borrow_mut().record(Some(Tile::O));
+        press_keys(&mut game, &[Key::Char('7'), Key::Char('4')]);
+        let mut restarted = match game.handle_input(InputEvent::Key(Key::Char('r'))) {
+            StateTransition::Switch(state) => state,
+            _ => panic!("the game was not restarted"),
+        };
+        // Every spot is free again, so x gets to complete the top row.
+        let mut transition = StateTransition::None;
+        for c in ['7', '4', '8', '5', '9'] {
+            transition = restarted.handle_input(InputEvent::Key(Key::Char(c)));
+        }
+        match transition {
+            StateTransition::Switch(state) => {
+                let outcome = state.get_game_outcome().unwrap();
+                assert!(outcome.moves == vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)]);
+            }
+            _ => panic!("the restarted game should have ended"),
+        }
+        assert_eq!((scoreboard.borrow().x, scoreboard.borrow().o), (1, 1));
+    }
+
+    /// Plays a game between two basic computers after seeding the random choices, and returns the tile of the starting player along with the spots played.
+    fn play_seeded_game(seed: u64) -> (Tile, Vec<PlayingPosition>) {
+        random::seed(seed);
+        let players = vec![
+            Player::new(
+                Box::new(BasicComputerPlayerController::new(Tile::X)),
+                Tile::X,
+            ),
+            Player::new(
+                Box::new(BasicComputerPlayerController::new(Tile::O)),
+                Tile::O,
+            ),
+        ];
+        let mut game = PlayingState::with_players(
+            players,
+            SharedScoreboard::default(),
+            SharedSettings::default(),
+        );
+        let first = game.get_current_player().tile;
+        loop {
+            if let StateTransition::Switch(state) = game.tick() {
+                return (first, state.get_game_outcome().unwrap().moves);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_game() {
+        for seed in 0..5 {
+            assert!(play_seeded_game(seed) == play_seeded_game(seed));
+        }
+    }
+
+    #[test]
+    fn fixed_first_mover_always_starts() {
+        for (first_mover, seed) in [(Tile::X, 0), (Tile::O, 0), (Tile::X, 1), (Tile::O, 1)] {
+            random::seed(seed);
+            let settings = SharedSettings::default();
+            settings.borrow_mut().first_mover = Some(first_mover);
+            let players = vec![
+                Player::new(
+                    Box::new(BasicComputerPlayerController::new(Tile::O)),
+                    Tile::O,
+                ),
+                Player::new(
+                    Box::new(BasicComputerPlayerController::new(Tile::X)),
+                    Tile::X,
+                ),
+            ];
+            let game = PlayingState::with_players(players, SharedScoreboard::default(), settings);
+            assert!(game.get_current_player().tile == first_mover);
+        }
+    }
+
+    #[test]
+    fn going_back_leaves_the_game_for_the_choice_of_the_opponent() {
+        let settings = SharedSettings::default();
+        let mut state_manager = StateManager::new(Box::new(MainMenuState::new(settings.clone())));
+        state_manager.push(Box::new(ChooseOpponentMenuState::new(
+            SharedScoreboard::default(),
+            settings,
+        )));
+        // Against a friend, playing x: the game is pushed on top of the choice of the opponent.
+        for line in ["1", "2"] {
+            let input = InputEvent::Line(line.to_string());
+            assert!(!state_manager.handle_input(input).ok().unwrap());
+        }
+        assert_eq!(state_manager.get_depth(), 3);
+        let input = InputEvent::Key(Key::Char('b'));
+        assert!(!state_manager.handle_input(input).ok().unwrap());
+        assert_eq!(state_manager.get_depth(), 2);
+        // Going back once more reaches the main menu.
+        let input = InputEvent::Line("8".to_string());
+        assert!(!state_manager.handle_input(input).ok().unwrap());
+        assert_eq!(state_manager.get_depth(), 1);
     }
 }
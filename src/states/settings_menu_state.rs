@@ -0,0 +1,388 @@
+use crate::{
+    game::{
+        board::{Tile, TileTheme},
+        players::{OpponentKind, MAX_DIFFICULTY},
+    },
+    input::{InputMode, KeyBindings},
+    menus::{Menu, MenuEntry, MenuEntryId, MenuState, MenuTheme},
+    rendering::Color,
+    rendering::{Error, Renderer},
+    settings::SharedSettings,
+    states::StateTransition,
+};
+use std::time::Duration;
+
+/// The menu in which the user changes the settings. Every entry shows the current value of its setting, and changes it when chosen.
+pub struct SettingsMenuState {
+    animate_win_entry: MenuEntryId,
+    arrow_key_menus_entry: MenuEntryId,
+    bell_entry: MenuEntryId,
+    brackets_entry: MenuEntryId,
+    cautious_easy_computer_entry: MenuEntryId,
+    computer_difficulty_entry: MenuEntryId,
+    confirm_quit_entry: MenuEntryId,
+    default_opponent_entry: MenuEntryId,
+    first_mover_entry: MenuEntryId,
+    high_contrast_entry: MenuEntryId,
+    highlight_entry: MenuEntryId,
+    key_bindings_entry: MenuEntryId,
+    labels_entry: MenuEntryId,
+    match_length_entry: MenuEntryId,
+    menu: Menu,
+    mouse_entry: MenuEntryId,
+    settings: SharedSettings,
+    strict_undo_entry: MenuEntryId,
+    tile_theme_entry: MenuEntryId,
+    time_limit_entry: MenuEntryId,
+    wrap_around_entry: MenuEntryId,
+}
+
+impl SettingsMenuState {
+    pub fn new(settings: SharedSettings) -> Self {
+        let mut menu = Menu::new();
+        let tile_theme_entry = menu.push(MenuEntry::new("", '1'));
+        let confirm_quit_entry = menu.push(MenuEntry::new("", '2'));
+        let default_opponent_entry = menu.push(MenuEntry::new("", '3'));
+        let animate_win_entry = menu.push(MenuEntry::new("", '4'));
+        let key_bindings_entry = menu.push(MenuEntry::new("", '5'));
+        let highlight_entry = menu.push(MenuEntry::new("", '6'));
+        let mouse_entry = menu.push(MenuEntry::new("", '7'));
+        let time_limit_entry = menu.push(MenuEntry::new("", '8'));
+        let arrow_key_menus_entry = menu.push(MenuEntry::new("", '9'));
+        let match_length_entry = menu.push(MenuEntry::new("", 'm'));
+        let high_contrast_entry = menu.push(MenuEntry::new("", 'h'));
+        let brackets_entry = menu.push(MenuEntry::new("", 'w'));
+        let strict_undo_entry = menu.push(MenuEntry::new("", 'u'));
+        let labels_entry = menu.push(MenuEntry::new("", 'l'));
+        let first_mover_entry = menu.push(MenuEntry::new("", 'f'));
+        let bell_entry = menu.push(MenuEntry::new("", 'e'));
+        let wrap_around_entry = menu.push(MenuEntry::new("", 'a'));
+        let computer_difficulty_entry = menu.push(MenuEntry::new("", 'd'));
+        let cautious_easy_computer_entry = menu.push(MenuEntry::new("", 'c'));
+        menu.push(MenuEntry::new("Go back", 'b'));
+        let mut state = SettingsMenuState {
+            animate_win_entry,
+            arrow_key_menus_entry,
+            bell_entry,
+            brackets_entry,
+            cautious_easy_computer_entry,
+            computer_difficulty_entry,
+            confirm_quit_entry,
+            default_opponent_entry,
+            first_mover_entry,
+            high_contrast_entry,
+            highlight_entry,
+            key_bindings_entry,
+            labels_entry,
+            match_length_entry,
+            menu,
+            mouse_entry,
+            settings,
+            strict_undo_entry,
+            tile_theme_entry,
+            time_limit_entry,
+            wrap_around_entry,
+        };
+        state.update_entries();
+        state
+    }
+
+    /// Updates the text of the entries so that they show the current settings.
+    fn update_entries(&mut self) {
+        let settings = self.settings.borrow();
+        let texts = [
+            (
+                self.tile_theme_entry,
+                format!(
+                    "Tiles: {}",
+                    if settings.tile_theme.o == TileTheme::unicode().o {
+                        "unicode"
+                    } else {
+                        "letters"
+                    }
+                ),
+            ),
+            (
+                self.confirm_quit_entry,
+                format!(
+                    "Confirm before quitting a game: {}",
+                    if settings.confirm_quit { "yes" } else { "no" }
+                ),
+            ),
+            (
+                self.default_opponent_entry,
+                format!(
+                    "Default opponent: {}",
+                    settings.default_opponent.get_description()
+                ),
+            ),
+            (
+                self.animate_win_entry,
+                format!(
+                    "Animate the winning line: {}",
+                    if settings.animate_win { "yes" } else { "no" }
+                ),
+            ),
+            (
+                self.key_bindings_entry,
+                format!(
+                    "Keys: {}",
+                    if settings.key_bindings == KeyBindings::keypad() {
+                        "numeric keypad (8, 4, 6 and 2 to move, 5 to play)"
+                    } else {
+                        "arrows (or h, j, k and l) to move, enter to play"
+                    }
+                ),
+            ),
+            (
+                self.highlight_entry,
+                format!(
+                    "Winning line color: {}",
+                    match settings.tile_theme.highlight {
+                        Color::Blue => "blue",
+                        Color::Magenta => "magenta",
+                        Color::Red => "red",
+                        Color::White => "white",
+                        _ => "green",
+                    }
+                ),
+            ),
+            (
+                self.mouse_entry,
+                format!(
+                    "Play by clicking the tiles: {}",
+                    if settings.mouse { "yes" } else { "no" }
+                ),
+            ),
+            (
+                self.time_limit_entry,
+                match settings.time_limit {
+                    Some(limit) => format!(
+                        "Time per player: {}:{:02}",
+                        limit.as_secs() / 60,
+                        limit.as_secs() % 60
+                    ),
+                    None => "Time per player: unlimited".to_string(),
+                },
+            ),
+            (
+                self.arrow_key_menus_entry,
+                format!(
+                    "Choose menu entries with the arrow keys: {}",
+                    if settings.arrow_key_menus {
+                        "yes"
+                    } else {
+                        "no"
+                    }
+                ),
+            ),
+            (
+                self.match_length_entry,
+                if settings.match_length > 1 {
+                    format!("Games: best of {}", settings.match_length)
+                } else {
+                    "Games: one at a time".to_string()
+                },
+            ),
+            (
+                self.high_contrast_entry,
+                format!(
+                    "High contrast colors: {}",
+                    if settings.high_contrast { "yes" } else { "no" }
+                ),
+            ),
+            (
+                self.strict_undo_entry,
+                format!(
+                    "Undo only the last move, before the next player moves: {}",
+                    if settings.strict_undo { "yes" } else { "no" }
+                ),
+            ),
+            (
+                self.labels_entry,
+                format!(
+                    "Letters and numbers around the board: {}",
+                    if settings.tile_theme.labels {
+                        "yes"
+                    } else {
+                        "no"
+                    }
+                ),
+            ),
+            (
+                self.brackets_entry,
+                format!(
+                    "Brackets around the winning line: {}",
+                    if settings.tile_theme.brackets {
+                        "yes"
+                    } else {
+                        "no"
+                    }
+                ),
+            ),
+            (
+                self.first_mover_entry,
+                format!(
+                    "First to move: {}",
+                    match settings.first_mover {
+                        Some(tile) => settings.tile_theme.get_symbol(tile),
+                        None => "picked at random",
+                    }
+                ),
+            ),
+            (
+                self.bell_entry,
+                format!(
+                    "Ring the bell on rejected moves and at the end of games: {}",
+                    if settings.bell { "yes" } else { "no" }
+                ),
+            ),
+            (
+                self.wrap_around_entry,
+                format!(
+                    "Move the cursor around the edges of the board: {}",
+                    if settings.wrap_around { "yes" } else { "no" }
+                ),
+            ),
+            (
+                self.computer_difficulty_entry,
+                format!(
+                    "Difficulty of the adjustable computer: {}/{}",
+                    settings.computer_difficulty, MAX_DIFFICULTY
+                ),
+            ),
+            (
+                self.cautious_easy_computer_entry,
+                format!(
+                    "Easy computer avoids losing on the next move: {}",
+                    if settings.cautious_easy_computer {
+                        "yes"
+                    } else {
+                        "no"
+                    }
+                ),
+            ),
+        ];
+        for (entry, text) in texts.iter() {
+            if let Some(entry) = self.menu.get_entry_mut(*entry) {
+                entry.text = text.clone();
+            }
+        }
+    }
+}
+
+impl MenuState for SettingsMenuState {
+    fn get_input_mode(&self) -> InputMode {
+        self.settings.borrow().get_menu_input_mode()
+    }
+
+    fn get_menu(&self) -> &Menu {
+        &self.menu
+    }
+
+    fn get_menu_theme(&self) -> MenuTheme {
+        self.settings.borrow().menu_theme.clone()
+    }
+
+    fn handle_selection(&mut self, entry: MenuEntryId) -> StateTransition {
+        {
+            let mut settings = self.settings.borrow_mut();
+            if entry == self.tile_theme_entry {
+                // Only the symbols change, the colors are kept.
+                let symbols = if settings.tile_theme.o == TileTheme::unicode().o {
+                    TileTheme::default()
+                } else {
+                    TileTheme::unicode()
+                };
+                settings.tile_theme = TileTheme {
+                    empty: symbols.empty,
+                    o: symbols.o,
+                    x: symbols.x,
+                    ..settings.tile_theme.clone()
+                };
+            } else if entry == self.confirm_quit_entry {
+                settings.confirm_quit = !settings.confirm_quit;
+            } else if entry == self.default_opponent_entry {
+                settings.default_opponent = match settings.default_opponent {
+                    OpponentKind::Friend => OpponentKind::EasyComputer,
+                    OpponentKind::EasyComputer => OpponentKind::MediumComputer,
+                    OpponentKind::MediumComputer => OpponentKind::UnbeatableComputer,
+                    OpponentKind::UnbeatableComputer => OpponentKind::DefensiveComputer,
+                    OpponentKind::DefensiveComputer => OpponentKind::AdjustableComputer,
+                    OpponentKind::AdjustableComputer => OpponentKind::Friend,
+                };
+            } else if entry == self.animate_win_entry {
+                settings.animate_win = !settings.animate_win;
+            } else if entry == self.highlight_entry {
+                settings.tile_theme.highlight = match settings.tile_theme.highlight {
+                    Color::Green => Color::Blue,
+                    Color::Blue => Color::Magenta,
+                    Color::Magenta => Color::Red,
+                    _ => Color::Green,
+                };
+            } else if entry == self.time_limit_entry {
+                settings.time_limit = match settings.time_limit.map(|l| l.as_secs()) {
+                    None => Some(Duration::from_secs(30)),
+                    Some(30) => Some(Duration::from_secs(60)),
+                    Some(60) => Some(Duration::from_secs(180)),
+                    _ => None,
+                };
+            } else if entry == self.arrow_key_menus_entry {
+                settings.arrow_key_menus = !settings.arrow_key_menus;
+            } else if entry == self.strict_undo_entry {
+                settings.strict_undo = !settings.strict_undo;
+            } else if entry == self.cautious_easy_computer_entry {
+                settings.cautious_easy_computer = !settings.cautious_easy_computer;
+            } else if entry == self.computer_difficulty_entry {
+                settings.computer_difficulty =
+                    (settings.computer_difficulty + 1) % (MAX_DIFFICULTY + 1);
+            } else if entry == self.wrap_around_entry {
+                settings.wrap_around = !settings.wrap_around;
+            } else if entry == self.bell_entry {
+                settings.bell = !settings.bell;
+            } else if entry == self.first_mover_entry {
+                settings.first_mover = match settings.first_mover {
+                    None => Some(Tile::X),
+                    Some(Tile::X) => Some(Tile::O),
+                    _ => None,
+                };
+            } else if entry == self.labels_entry {
+                settings.tile_theme.labels = !settings.tile_theme.labels;
+            } else if entry == self.brackets_entry {
+                settings.tile_theme.brackets = !settings.tile_theme.brackets;
+            } else if entry == self.high_contrast_entry {
+                settings.high_contrast = !settings.high_contrast;
+                if settings.high_contrast {
+                    settings.menu_theme = MenuTheme::high_contrast();
+                    settings.tile_theme = settings.tile_theme.with_high_contrast();
+                } else {
+                    settings.menu_theme = MenuTheme::default();
+                    settings.tile_theme = settings.tile_theme.with_default_colors();
+                }
+            } else if entry == self.match_length_entry {
+                settings.match_length = match settings.match_length {
+                    1 => 3,
+                    3 => 5,
+                    _ => 1,
+                };
+            } else if entry == self.mouse_entry {
+                settings.mouse = !settings.mouse;
+            } else if entry == self.key_bindings_entry {
+                settings.key_bindings = if settings.key_bindings == KeyBindings::keypad() {
+                    KeyBindings::default()
+                } else {
+                    KeyBindings::keypad()
+                };
+            } else {
+                return StateTransition::Pop;
+            }
+        }
+        self.update_entries();
+        StateTransition::None
+    }
+
+    fn render_header(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        renderer.write("Settings\n\n")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{
+    input::{InputEvent, InputMode},
+    rendering::{render_centered, Error, Renderer},
+    settings::SharedSettings,
+    states::{main_menu_state::MainMenuState, State, StateTransition},
+};
+
+/// The logo shown by the splash screen.
+const LOGO: [&str; 7] = [
+    r" _____ _        _____            _____          ",
+    r"|_   _(_) ___  |_   _|_ _  ___  |_   _|__   ___ ",
+    r"  | | | |/ __|   | |/ _` |/ __|   | |/ _ \ / _ \",
+    r"  | | | | (__    | | (_| | (__    | | (_) |  __/",
+    r"  |_| |_|\___|   |_|\__,_|\___|   |_|\___/ \___|",
+    r"",
+    r"             Press any key to start             ",
+];
+
+/// The screen shown at startup, with the logo of the game centered on it. Any key leads to the main menu.
+pub struct SplashState {
+    settings: SharedSettings,
+}
+
+impl SplashState {
+    pub fn new(settings: SharedSettings) -> Self {
+        SplashState { settings }
+    }
+}
+
+impl State for SplashState {
+    fn get_input_mode(&self) -> InputMode {
+        InputMode::Key
+    }
+
+    fn handle_input(&mut self, input: InputEvent) -> StateTransition {
+        match input {
+            InputEvent::Key(_) => {
+                StateTransition::Switch(Box::new(MainMenuState::new(self.settings.clone())))
+            }
+            _ => StateTransition::None,
+        }
+    }
+
+    fn render(&self, renderer: &dyn Renderer) -> Result<(), Error> {
+        let (columns, rows) = renderer.get_screen_size();
+        let width = LOGO.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16;
+        // On a screen too small for the logo, just show the title.
+        if width > columns || LOGO.len() as u16 > rows {
+            renderer.clear()?;
+            renderer.write("Tic Tac Toe\n\nPress any key to start")?;
+            return Ok(());
+        }
+        render_centered(renderer, |renderer| {
+            renderer.clear()?;
+            renderer.write(&LOGO.join("\n"))?;
+            Ok(())
+        })
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input::Key, rendering::BufferRenderer};
+
+    #[test]
+    fn any_key_leaves_the_splash_screen() {
+        let mut splash = SplashState::new(SharedSettings::default());
+        assert!(matches!(
+            splash.handle_input(InputEvent::Resize),
+            StateTransition::None
+        ));
+        assert!(matches!(
+            splash.handle_input(InputEvent::Key(Key::Char(' '))),
+            StateTransition::Switch(_)
+        ));
+    }
+
+    #[test]
+    fn the_logo_is_shown_if_it_fits() {
+        let splash = SplashState::new(SharedSettings::default());
+        let renderer = BufferRenderer::new((80, 24));
+        splash.render(&renderer).ok().unwrap();
+        assert!(renderer.contents().contains(LOGO[1]));
+        let renderer = BufferRenderer::new((30, 10));
+        splash.render(&renderer).ok().unwrap();
+        assert!(!renderer.contents().contains(LOGO[1]));
+        assert!(renderer.contents().starts_with("Tic Tac Toe"));
+    }
+}